@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+// Things the player can do with the keyboard, the mouse or a gamepad. Systems ask for actions
+// through `Actions` instead of reading raw input, so every action can be rebound
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize)]
+pub enum Action {
+    // Open (or close) the play menu of the pet
+    OpenPetMenu,
+    // Close the current menu
+    Cancel,
+    // Switch the desktop widget mode on and off
+    ToggleWidget,
+    // Hold to drag the pet (and the window with it) in widget mode
+    GrabPet,
+    // Move the focus between the buttons of a menu
+    NavigateUp,
+    NavigateDown,
+    NavigateLeft,
+    NavigateRight,
+    // Click the focused button
+    Confirm,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::OpenPetMenu,
+        Action::Cancel,
+        Action::ToggleWidget,
+        Action::GrabPet,
+        Action::NavigateUp,
+        Action::NavigateDown,
+        Action::NavigateLeft,
+        Action::NavigateRight,
+        Action::Confirm,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::OpenPetMenu => "Pet menu",
+            Action::Cancel => "Cancel",
+            Action::ToggleWidget => "Widget",
+            Action::GrabPet => "Drag pet",
+            Action::NavigateUp => "Up",
+            Action::NavigateDown => "Down",
+            Action::NavigateLeft => "Left",
+            Action::NavigateRight => "Right",
+            Action::Confirm => "Confirm",
+        }
+    }
+}
+
+// A key, mouse button or gamepad button an action is bound to
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // A button of any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn name(self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Mouse(button) => format!("{button:?} click"),
+            Binding::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+
+    fn is_gamepad(self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+}
+
+// Bindings of every action, saved in the config file
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<Action, Vec<Binding>>",
+    into = "BTreeMap<Action, Vec<Binding>>"
+)]
+pub struct InputBindings(BTreeMap<Action, Vec<Binding>>);
+
+// Config files written before an action existed get its default bindings
+impl From<BTreeMap<Action, Vec<Binding>>> for InputBindings {
+    fn from(bindings: BTreeMap<Action, Vec<Binding>>) -> Self {
+        let mut all = InputBindings::default();
+        all.0.extend(bindings);
+        all
+    }
+}
+
+impl From<InputBindings> for BTreeMap<Action, Vec<Binding>> {
+    fn from(bindings: InputBindings) -> Self {
+        bindings.0
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings(BTreeMap::from([
+            (
+                Action::OpenPetMenu,
+                vec![
+                    Binding::Mouse(MouseButton::Right),
+                    Binding::Gamepad(GamepadButtonType::North),
+                ],
+            ),
+            (
+                Action::Cancel,
+                vec![
+                    Binding::Key(KeyCode::Escape),
+                    Binding::Gamepad(GamepadButtonType::East),
+                ],
+            ),
+            (
+                Action::ToggleWidget,
+                vec![
+                    Binding::Key(KeyCode::F2),
+                    Binding::Gamepad(GamepadButtonType::Select),
+                ],
+            ),
+            (Action::GrabPet, vec![Binding::Mouse(MouseButton::Left)]),
+            (
+                Action::NavigateUp,
+                vec![
+                    Binding::Key(KeyCode::Up),
+                    Binding::Gamepad(GamepadButtonType::DPadUp),
+                ],
+            ),
+            (
+                Action::NavigateDown,
+                vec![
+                    Binding::Key(KeyCode::Down),
+                    Binding::Gamepad(GamepadButtonType::DPadDown),
+                ],
+            ),
+            (
+                Action::NavigateLeft,
+                vec![
+                    Binding::Key(KeyCode::Left),
+                    Binding::Gamepad(GamepadButtonType::DPadLeft),
+                ],
+            ),
+            (
+                Action::NavigateRight,
+                vec![
+                    Binding::Key(KeyCode::Right),
+                    Binding::Gamepad(GamepadButtonType::DPadRight),
+                ],
+            ),
+            (
+                Action::Confirm,
+                vec![
+                    Binding::Key(KeyCode::Return),
+                    Binding::Gamepad(GamepadButtonType::South),
+                ],
+            ),
+        ]))
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    // Bind `action` to `binding` instead of its current keyboard and mouse bindings, or
+    // instead of its gamepad bindings when `binding` is a gamepad button
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.0.entry(action).or_default();
+        bindings.retain(|bound| bound.is_gamepad() != binding.is_gamepad());
+        bindings.insert(0, binding);
+    }
+}
+
+// Present while waiting for the key or button to bind to an action. No action is triggered
+// meanwhile, so the key can be bound whatever it currently does
+#[derive(Resource)]
+pub struct Rebinding(pub Action);
+
+// Edges of an input that can be asked for
+#[derive(Clone, Copy)]
+enum Edge {
+    JustPressed,
+    Held,
+    JustReleased,
+}
+
+fn edge<T: Copy + Eq + Hash + Send + Sync + 'static>(
+    input: &Input<T>,
+    value: T,
+    edge: Edge,
+) -> bool {
+    match edge {
+        Edge::JustPressed => input.just_pressed(value),
+        Edge::Held => input.pressed(value),
+        Edge::JustReleased => input.just_released(value),
+    }
+}
+
+// State of the actions this frame, from the raw input and the bindings
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    bindings: Res<'w, InputBindings>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepads: Res<'w, Gamepads>,
+    rebinding: Option<Res<'w, Rebinding>>,
+}
+
+impl<'w> Actions<'w> {
+    fn is(&self, binding: Binding, which: Edge) -> bool {
+        if self.rebinding.is_some() {
+            return false;
+        }
+        match binding {
+            Binding::Key(key) => edge(&self.keys, key, which),
+            Binding::Mouse(button) => edge(&self.mouse_buttons, button, which),
+            Binding::Gamepad(button) => self.gamepads.iter().any(|gamepad| {
+                edge(
+                    &self.gamepad_buttons,
+                    GamepadButton::new(gamepad, button),
+                    which,
+                )
+            }),
+        }
+    }
+
+    // Binding that triggered `action` this frame, if any
+    pub fn just_pressed_by(&self, action: Action) -> Option<Binding> {
+        self.bindings
+            .get(action)
+            .iter()
+            .copied()
+            .find(|binding| self.is(*binding, Edge::JustPressed))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed_by(action).is_some()
+    }
+
+    pub fn held(&self, action: Action) -> bool {
+        let bindings = self.bindings.get(action);
+        bindings.iter().any(|binding| self.is(*binding, Edge::Held))
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        let bindings = self.bindings.get(action);
+        bindings
+            .iter()
+            .any(|binding| self.is(*binding, Edge::JustReleased))
+    }
+
+    // Whatever key or button was pressed this frame, to bind it to an action
+    pub fn any_just_pressed(&self) -> Option<Binding> {
+        self.keys
+            .get_just_pressed()
+            .next()
+            .map(|key| Binding::Key(*key))
+            .or_else(|| {
+                let button = self.mouse_buttons.get_just_pressed().next()?;
+                Some(Binding::Mouse(*button))
+            })
+            .or_else(|| {
+                let button = self.gamepad_buttons.get_just_pressed().next()?;
+                Some(Binding::Gamepad(button.button_type))
+            })
+    }
+}
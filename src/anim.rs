@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{Extent3d, TextureDimension},
+};
+use serde::Deserialize;
+
+use super::growth::{Growth, GrowthStage};
+use super::loading::{asset_changed, RonLoader};
+use super::pet::{Pet, PetActivity, PetProfile, Species};
+
+// This plugin animates the pet with the sprite sheets described in
+// `assets/animations/pets.sheets.ron`
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SpriteSheets>()
+            .add_asset_loader(RonLoader::<SpriteSheets>::new(&["sheets.ron"]))
+            .init_resource::<PetAtlases>()
+            .add_startup_system(load_sprite_sheets)
+            .add_systems((build_atlases, attach_animation, select_clip, play_animation).chain());
+    }
+}
+
+// Sprite sheet of every species, as read from the data file
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "5b7a4c1e-3f0d-4b8e-9a51-2d6c8e0f7a13"]
+#[serde(transparent)]
+pub struct SpriteSheets(pub HashMap<Species, SheetDef>);
+
+// Layout of one sprite sheet and the clips it contains
+#[derive(Deserialize, Debug)]
+pub struct SheetDef {
+    pub texture: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    // Scale applied to the sprite, small pixel art sheets need to be blown up
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    // Scale of the growth stages relative to `scale`, stages left out use 1
+    #[serde(default)]
+    pub stage_scale: HashMap<GrowthStage, f32>,
+    // Clips named "<stage>_<clip>" (like "egg_idle") replace "<clip>" during that stage
+    pub clips: HashMap<String, ClipDef>,
+}
+
+impl SheetDef {
+    // The first frame of the sheet as an image of its own, for the UI which can't show a part
+    // of a texture. `texture` is the loaded image of `self.texture`
+    pub fn first_frame(&self, texture: &Image) -> Image {
+        let size = texture.texture_descriptor.size;
+        let format = texture.texture_descriptor.format;
+        let pixel = format.describe().block_size as usize;
+        let width = (self.tile_size.0 as u32).min(size.width);
+        let height = (self.tile_size.1 as u32).min(size.height);
+        let data = texture
+            .data
+            .chunks(size.width as usize * pixel)
+            .take(height as usize)
+            .flat_map(|row| &row[..width as usize * pixel])
+            .copied()
+            .collect();
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            format,
+        )
+    }
+
+    fn scale_for(&self, stage: GrowthStage) -> f32 {
+        self.scale * self.stage_scale.get(&stage).copied().unwrap_or(1.0)
+    }
+
+    // Clip to play for `clip` during `stage`, falling back to the clip shared by all stages
+    // and then to the idle one
+    fn clip_for(&self, stage: GrowthStage, clip: &str) -> Option<&ClipDef> {
+        let staged = format!("{}_{clip}", stage.name().to_lowercase());
+        self.clips
+            .get(&staged)
+            .or_else(|| self.clips.get(clip))
+            .or_else(|| self.clips.get("idle"))
+    }
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+// A named range of frames of a sprite sheet
+#[derive(Deserialize, Clone, Debug)]
+pub struct ClipDef {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+    #[serde(default)]
+    pub mode: PlayMode,
+}
+
+// What happens when a clip reaches its last frame
+#[derive(Deserialize, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum PlayMode {
+    // Start again from the first frame
+    #[default]
+    Loop,
+    // Stay on the last frame
+    Once,
+    // Play backwards down to the first frame, then forward again
+    PingPong,
+}
+
+#[derive(Resource, Deref)]
+struct PetSheetsHandle(Handle<SpriteSheets>);
+
+// Texture atlas of every species, built once the sprite sheets are loaded
+#[derive(Resource, Default)]
+struct PetAtlases(HashMap<Species, Handle<TextureAtlas>>);
+
+// Clip currently played by a sprite
+#[derive(Component, Debug)]
+pub struct SpriteAnimation {
+    pub clip: String,
+    frame: usize,
+    backwards: bool,
+    timer: Timer,
+}
+
+impl SpriteAnimation {
+    fn new(clip: &str) -> Self {
+        SpriteAnimation {
+            clip: clip.to_string(),
+            frame: 0,
+            backwards: false,
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        }
+    }
+
+    // Switch to another clip, restarting from its first frame
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip {
+            *self = SpriteAnimation::new(clip);
+        }
+    }
+
+    // Move to the next frame of `clip`, following its play mode
+    fn advance(&mut self, clip: &ClipDef) {
+        let len = clip.last.saturating_sub(clip.first) + 1;
+        if len == 1 {
+            self.frame = 0;
+            return;
+        }
+        match clip.mode {
+            PlayMode::Loop => self.frame = (self.frame + 1) % len,
+            PlayMode::Once => self.frame = (self.frame + 1).min(len - 1),
+            PlayMode::PingPong => {
+                if self.backwards && self.frame == 0 {
+                    self.backwards = false;
+                } else if !self.backwards && self.frame + 1 >= len {
+                    self.backwards = true;
+                }
+                if self.backwards {
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }
+        }
+    }
+}
+
+impl PetActivity {
+    // Name of the clip played while the pet does this activity
+    pub fn clip(self) -> &'static str {
+        match self {
+            PetActivity::Idle | PetActivity::Awake | PetActivity::Eating => "idle",
+            PetActivity::Sleeping => "sleep",
+            PetActivity::Playing => "jump",
+        }
+    }
+}
+
+pub const SPRITE_SHEETS_PATH: &str = "animations/pets.sheets.ron";
+
+fn load_sprite_sheets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PetSheetsHandle(asset_server.load(SPRITE_SHEETS_PATH)));
+}
+
+// (Re)build the texture atlases when the data file is loaded or changed on disk
+fn build_atlases(
+    mut events: EventReader<AssetEvent<SpriteSheets>>,
+    handle: Res<PetSheetsHandle>,
+    sheets: Res<Assets<SpriteSheets>>,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    mut pet_atlases: ResMut<PetAtlases>,
+    mut pets: Query<(&PetProfile, &mut Handle<TextureAtlas>), With<Pet>>,
+) {
+    let reloaded = asset_changed(&mut events, &handle);
+    let Some(sheets) = reloaded.then(|| sheets.get(&handle)).flatten() else {
+        return;
+    };
+    pet_atlases.0.clear();
+    for (species, sheet) in &sheets.0 {
+        let atlas = TextureAtlas::from_grid(
+            asset_server.load(sheet.texture.as_str()),
+            Vec2::new(sheet.tile_size.0, sheet.tile_size.1),
+            sheet.columns,
+            sheet.rows,
+            None,
+            None,
+        );
+        pet_atlases.0.insert(*species, atlases.add(atlas));
+    }
+    // Pets that are already animated switch to the new atlases
+    for (profile, mut atlas) in &mut pets {
+        if let Some(new_atlas) = pet_atlases.0.get(&profile.species) {
+            *atlas = new_atlas.clone();
+        }
+    }
+}
+
+// Turn the still sprite of a freshly spawned pet into an animated one
+fn attach_animation(
+    mut commands: Commands,
+    pet_atlases: Res<PetAtlases>,
+    pets: Query<(Entity, &PetProfile, &PetActivity), (With<Pet>, Without<SpriteAnimation>)>,
+) {
+    for (entity, profile, activity) in &pets {
+        let Some(atlas) = pet_atlases.0.get(&profile.species) else {
+            continue;
+        };
+        commands
+            .entity(entity)
+            .remove::<(Sprite, Handle<Image>)>()
+            .insert((
+                TextureAtlasSprite::default(),
+                atlas.clone(),
+                SpriteAnimation::new(activity.clip()),
+            ));
+    }
+}
+
+// Play the clip matching what the pet is doing
+fn select_clip(mut pets: Query<(&PetActivity, &mut SpriteAnimation), Changed<PetActivity>>) {
+    for (activity, mut animation) in &mut pets {
+        animation.play(activity.clip());
+    }
+}
+
+fn play_animation(
+    time: Res<Time>,
+    handle: Res<PetSheetsHandle>,
+    sheets: Res<Assets<SpriteSheets>>,
+    mut sprites: Query<(
+        &PetProfile,
+        &Growth,
+        &mut SpriteAnimation,
+        &mut TextureAtlasSprite,
+        &mut Transform,
+    )>,
+) {
+    let Some(sheets) = sheets.get(&handle) else {
+        return;
+    };
+    for (profile, growth, mut animation, mut sprite, mut transform) in &mut sprites {
+        let Some(sheet) = sheets.0.get(&profile.species) else {
+            continue;
+        };
+        transform.scale = Vec3::splat(sheet.scale_for(growth.stage));
+        let Some(clip) = sheet.clip_for(growth.stage, &animation.clip) else {
+            continue;
+        };
+        // The clip may have changed under the animation when the pet grew up
+        animation.frame = animation.frame.min(clip.last.saturating_sub(clip.first));
+        let frame_time = 1.0 / clip.fps.max(f32::EPSILON);
+        if animation.timer.duration().as_secs_f32() != frame_time {
+            animation.timer = Timer::from_seconds(frame_time, TimerMode::Repeating);
+        }
+        let ticks = animation
+            .timer
+            .tick(time.delta())
+            .times_finished_this_tick();
+        for _ in 0..ticks {
+            animation.advance(clip);
+        }
+        sprite.index = clip.first + animation.frame;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_resource::TextureFormat;
+
+    #[test]
+    fn first_frame_is_cut_from_the_strip() {
+        // A 3x2 strip of three 1x2 frames, every pixel holds its column in the red channel
+        let data = (0..2)
+            .flat_map(|_| (0..3u8).flat_map(|x| [x, 0, 0, 255]))
+            .collect();
+        let strip = Image::new(
+            Extent3d {
+                width: 3,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let sheet: SheetDef = ron::from_str(
+            r#"(texture: "strip.png", tile_size: (1.0, 2.0), columns: 3, rows: 1, clips: {})"#,
+        )
+        .unwrap();
+        let frame = sheet.first_frame(&strip);
+        assert_eq!(frame.size(), Vec2::new(1.0, 2.0));
+        assert_eq!(frame.data, vec![0, 0, 0, 255, 0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::{Deserialize, Serialize};
+
+use super::loading::RonLoader;
+use super::pet::{Pet, PetAge, PetFeedback, PetProfile, PetStats, Species, STAT_MAX};
+use super::save::GameInProgress;
+
+// This plugin makes the pet grow up through the stages described in
+// `assets/data/pets.growth.ron`, and picks its adult form from how well it was cared for
+pub struct GrowthPlugin;
+
+impl Plugin for GrowthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<GrowthTable>()
+            .add_asset_loader(RonLoader::<GrowthTable>::new(&["growth.ron"]))
+            .add_startup_system(load_growth_table)
+            .add_systems(
+                (
+                    track_care.run_if(resource_exists::<GameInProgress>()),
+                    grow,
+                    tint_evolved,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Stages of the life of a pet, in order
+#[derive(
+    Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize,
+)]
+pub enum GrowthStage {
+    #[default]
+    Egg,
+    Baby,
+    Juvenile,
+    Adult,
+    Elder,
+}
+
+impl GrowthStage {
+    pub const ALL: [GrowthStage; 5] = [
+        GrowthStage::Egg,
+        GrowthStage::Baby,
+        GrowthStage::Juvenile,
+        GrowthStage::Adult,
+        GrowthStage::Elder,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GrowthStage::Egg => "Egg",
+            GrowthStage::Baby => "Baby",
+            GrowthStage::Juvenile => "Juvenile",
+            GrowthStage::Adult => "Adult",
+            GrowthStage::Elder => "Elder",
+        }
+    }
+}
+
+// How the stages and the evolutions of every species work, as read from the data file
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "8e3b1f6a-27d4-4c95-b0a8-5f9c2d7e4a61"]
+#[serde(transparent)]
+pub struct GrowthTable(pub HashMap<Species, SpeciesGrowth>);
+
+#[derive(Deserialize, Debug)]
+pub struct SpeciesGrowth {
+    pub stages: HashMap<GrowthStage, StageDef>,
+    // Adult forms, the first one whose `min_care` is reached is picked
+    pub evolutions: Vec<EvolutionDef>,
+}
+
+impl SpeciesGrowth {
+    // Stage of a pet of this age. A stage lasts until its `until` age, the last one forever
+    pub fn stage_at(&self, age: f32) -> GrowthStage {
+        GrowthStage::ALL
+            .into_iter()
+            .find(|stage| {
+                self.stages
+                    .get(stage)
+                    .and_then(|def| def.until)
+                    .is_none_or(|until| age < until)
+            })
+            .unwrap_or(GrowthStage::Elder)
+    }
+
+    pub fn evolution(&self, care: f32) -> Option<&EvolutionDef> {
+        self.evolutions
+            .iter()
+            .find(|evolution| care >= evolution.min_care)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct StageDef {
+    // Age (in seconds) at which the pet moves on to the next stage
+    #[serde(default)]
+    pub until: Option<f32>,
+    // Highest value the stats can reach during this stage
+    pub stat_cap: f32,
+    // Multiplier of the decay rates during this stage
+    pub decay_rate: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct EvolutionDef {
+    pub name: String,
+    // Average care (0 to 1) needed for this form
+    pub min_care: f32,
+    // Colour multiplied with the sprite of the evolved pet
+    #[serde(default = "default_tint")]
+    pub tint: (f32, f32, f32),
+}
+
+fn default_tint() -> (f32, f32, f32) {
+    (1.0, 1.0, 1.0)
+}
+
+// Where the pet is in its life, saved with it
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Growth {
+    pub stage: GrowthStage,
+    // Adult form picked when the pet grew up, `None` before that
+    pub evolution: Option<String>,
+    pub tint: (f32, f32, f32),
+    // Care level (0 to 1) summed over every second it was tracked, and the number of seconds
+    pub care_total: f32,
+    pub care_time: f32,
+    // Modifiers of the current stage. They are kept here so that they also apply to the time
+    // simulated while the game was closed
+    pub stat_cap: f32,
+    pub decay_rate: f32,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Growth {
+            stage: GrowthStage::default(),
+            evolution: None,
+            tint: default_tint(),
+            care_total: 0.0,
+            care_time: 0.0,
+            stat_cap: STAT_MAX,
+            decay_rate: 1.0,
+        }
+    }
+}
+
+impl Growth {
+    // Record `seconds` spent with the given stats
+    pub fn track(&mut self, stats: &PetStats, seconds: f32) {
+        self.care_total += stats.care() * seconds;
+        self.care_time += seconds;
+    }
+
+    // How well the pet was cared for on average, from 0 to 1
+    pub fn average_care(&self) -> f32 {
+        if self.care_time > 0.0 {
+            self.care_total / self.care_time
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+struct GrowthTableHandle(Handle<GrowthTable>);
+
+pub const GROWTH_TABLE_PATH: &str = "data/pets.growth.ron";
+
+fn load_growth_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GrowthTableHandle(asset_server.load(GROWTH_TABLE_PATH)));
+}
+
+fn track_care(time: Res<Time>, mut pets: Query<(&PetStats, &mut Growth), With<Pet>>) {
+    for (stats, mut growth) in &mut pets {
+        growth.track(stats, time.delta_seconds());
+    }
+}
+
+// Move the pet to the stage matching its age, evolving it when it becomes an adult
+fn grow(
+    handle: Res<GrowthTableHandle>,
+    tables: Res<Assets<GrowthTable>>,
+    mut feedback: EventWriter<PetFeedback>,
+    mut pets: Query<(&PetProfile, &PetAge, &mut Growth), With<Pet>>,
+) {
+    let Some(table) = tables.get(&handle) else {
+        return;
+    };
+    for (profile, age, mut growth) in &mut pets {
+        let Some(species) = table.0.get(&profile.species) else {
+            continue;
+        };
+        let stage = species.stage_at(age.0);
+        let Some(def) = species.stages.get(&stage) else {
+            continue;
+        };
+        // Nothing to do until the pet reaches its next stage (or the data file changes)
+        if growth.stage == stage
+            && growth.stat_cap == def.stat_cap
+            && growth.decay_rate == def.decay_rate
+        {
+            continue;
+        }
+        let grew_up = growth.stage != stage;
+        growth.stage = stage;
+        growth.stat_cap = def.stat_cap;
+        growth.decay_rate = def.decay_rate;
+        if stage >= GrowthStage::Adult && growth.evolution.is_none() {
+            if let Some(evolution) = species.evolution(growth.average_care()) {
+                growth.evolution = Some(evolution.name.clone());
+                growth.tint = evolution.tint;
+                feedback.send(PetFeedback(format!(
+                    "{} evolved into a {}!",
+                    profile.name, evolution.name
+                )));
+                continue;
+            }
+        }
+        if grew_up {
+            feedback.send(PetFeedback(format!(
+                "{} is now {}",
+                profile.name,
+                stage.name().to_lowercase()
+            )));
+        }
+    }
+}
+
+// Colour the sprite of evolved pets. The care tracking changes `Growth` every frame, so the
+// colour is compared instead of relying on change detection
+fn tint_evolved(mut pets: Query<(&Growth, &mut TextureAtlasSprite), With<Pet>>) {
+    for (growth, mut sprite) in &mut pets {
+        let (r, g, b) = growth.tint;
+        let color = Color::rgb(r, g, b);
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(until: Option<f32>) -> StageDef {
+        StageDef {
+            until,
+            stat_cap: STAT_MAX,
+            decay_rate: 1.0,
+        }
+    }
+
+    fn evolution(name: &str, min_care: f32) -> EvolutionDef {
+        EvolutionDef {
+            name: name.to_string(),
+            min_care,
+            tint: default_tint(),
+        }
+    }
+
+    fn species() -> SpeciesGrowth {
+        SpeciesGrowth {
+            stages: HashMap::from([
+                (GrowthStage::Egg, stage(Some(10.0))),
+                (GrowthStage::Baby, stage(Some(20.0))),
+                (GrowthStage::Juvenile, stage(Some(30.0))),
+                (GrowthStage::Adult, stage(None)),
+            ]),
+            evolutions: vec![
+                evolution("Golden", 0.8),
+                evolution("Plain", 0.4),
+                evolution("Scruffy", 0.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn stages_follow_the_age() {
+        let species = species();
+        assert_eq!(species.stage_at(0.0), GrowthStage::Egg);
+        assert_eq!(species.stage_at(9.9), GrowthStage::Egg);
+        assert_eq!(species.stage_at(10.0), GrowthStage::Baby);
+        assert_eq!(species.stage_at(20.0), GrowthStage::Juvenile);
+        assert_eq!(species.stage_at(29.9), GrowthStage::Juvenile);
+        // The adult stage has no end, so the pet never becomes an elder
+        assert_eq!(species.stage_at(30.0), GrowthStage::Adult);
+        assert_eq!(species.stage_at(1.0e6), GrowthStage::Adult);
+    }
+
+    #[test]
+    fn evolution_depends_on_care() {
+        let species = species();
+        let name = |care| {
+            species
+                .evolution(care)
+                .map(|evolution| evolution.name.as_str())
+        };
+        assert_eq!(name(1.0), Some("Golden"));
+        assert_eq!(name(0.8), Some("Golden"));
+        assert_eq!(name(0.79), Some("Plain"));
+        assert_eq!(name(0.4), Some("Plain"));
+        assert_eq!(name(0.1), Some("Scruffy"));
+
+        let picky = SpeciesGrowth {
+            stages: HashMap::new(),
+            evolutions: vec![evolution("Golden", 0.8)],
+        };
+        assert!(picky.evolution(0.5).is_none());
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::loading::asset_changed;
+use super::shop::{CatalogueHandle, ItemCatalogue, ItemDef};
+
+// This plugin holds the items owned by the player and the definition of every item
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inventory>()
+            .init_resource::<ItemRegistry>()
+            .add_system(build_registry);
+    }
+}
+
+// Number of different stacks the inventory can hold
+pub const INVENTORY_SLOTS: usize = 20;
+
+// Items every new game starts with, so the pet can be fed before visiting the shop
+const STARTER_ITEMS: [(&str, u32); 2] = [("kibble", 5), ("water", 5)];
+
+// Definition of every item, keyed by id. Filled from the catalogue once it is loaded
+#[derive(Resource, Default, Debug)]
+pub struct ItemRegistry(HashMap<String, ItemDef>);
+
+impl ItemRegistry {
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.0.get(id)
+    }
+}
+
+// Some quantity of one item, never more than the `max_stack` of the item
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemStack {
+    pub id: String,
+    pub quantity: u32,
+}
+
+// Items owned by the player, in the order they were first added
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Inventory {
+    stacks: Vec<ItemStack>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NotEnoughItems {
+    pub id: String,
+    pub owned: u32,
+}
+
+impl fmt::Display for NotEnoughItems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "only {} {} left", self.owned, self.id)
+    }
+}
+
+impl Inventory {
+    pub fn starter() -> Self {
+        Inventory {
+            stacks: STARTER_ITEMS
+                .iter()
+                .map(|(id, quantity)| ItemStack {
+                    id: id.to_string(),
+                    quantity: *quantity,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn count(&self, id: &str) -> u32 {
+        self.stacks
+            .iter()
+            .filter(|stack| stack.id == id)
+            .map(|stack| stack.quantity)
+            .sum()
+    }
+
+    // Every item owned with its total quantity, in inventory order
+    pub fn items(&self) -> Vec<(&str, u32)> {
+        let mut items: Vec<(&str, u32)> = Vec::new();
+        for stack in &self.stacks {
+            match items.iter_mut().find(|(id, _)| *id == stack.id) {
+                Some((_, quantity)) => *quantity += stack.quantity,
+                None => items.push((&stack.id, stack.quantity)),
+            }
+        }
+        items
+    }
+
+    // Add `quantity` of `item`, topping up the existing stacks before using new slots.
+    // Returns the quantity that didn't fit
+    pub fn add(&mut self, item: &ItemDef, quantity: u32) -> u32 {
+        let max_stack = item.max_stack.max(1);
+        let mut left = quantity;
+        for stack in self.stacks.iter_mut().filter(|stack| stack.id == item.id) {
+            let added = left.min(max_stack.saturating_sub(stack.quantity));
+            stack.quantity += added;
+            left -= added;
+        }
+        while left > 0 && self.stacks.len() < INVENTORY_SLOTS {
+            let added = left.min(max_stack);
+            self.stacks.push(ItemStack {
+                id: item.id.clone(),
+                quantity: added,
+            });
+            left -= added;
+        }
+        left
+    }
+
+    // Take `quantity` of an item out, starting with the last stacks. Nothing is taken when
+    // there isn't enough of it
+    pub fn remove(&mut self, id: &str, quantity: u32) -> Result<(), NotEnoughItems> {
+        let owned = self.count(id);
+        if owned < quantity {
+            return Err(NotEnoughItems {
+                id: id.to_string(),
+                owned,
+            });
+        }
+        let mut left = quantity;
+        for stack in self.stacks.iter_mut().rev().filter(|stack| stack.id == id) {
+            let removed = left.min(stack.quantity);
+            stack.quantity -= removed;
+            left -= removed;
+        }
+        self.stacks.retain(|stack| stack.quantity > 0);
+        Ok(())
+    }
+}
+
+// (Re)build the registry when the catalogue is loaded or changed on disk
+fn build_registry(
+    mut events: EventReader<AssetEvent<ItemCatalogue>>,
+    handle: Res<CatalogueHandle>,
+    catalogues: Res<Assets<ItemCatalogue>>,
+    mut registry: ResMut<ItemRegistry>,
+) {
+    let reloaded = asset_changed(&mut events, &handle);
+    let Some(catalogue) = reloaded.then(|| catalogues.get(&handle)).flatten() else {
+        return;
+    };
+    registry.0 = catalogue
+        .0
+        .iter()
+        .map(|item| (item.id.clone(), item.clone()))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shop::{ItemEffect, ItemKind};
+
+    fn item(id: &str, max_stack: u32) -> ItemDef {
+        ItemDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: ItemKind::Food,
+            price: 1,
+            max_stack,
+            effect: ItemEffect::default(),
+            cures: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn add_fills_existing_stack_first() {
+        let kibble = item("kibble", 10);
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.add(&kibble, 4), 0);
+        assert_eq!(inventory.add(&kibble, 3), 0);
+        assert_eq!(inventory.stacks.len(), 1);
+        assert_eq!(inventory.count("kibble"), 7);
+    }
+
+    #[test]
+    fn add_spills_into_new_stacks() {
+        let kibble = item("kibble", 10);
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.add(&kibble, 25), 0);
+        let quantities: Vec<u32> = inventory
+            .stacks
+            .iter()
+            .map(|stack| stack.quantity)
+            .collect();
+        assert_eq!(quantities, [10, 10, 5]);
+        assert_eq!(inventory.items(), [("kibble", 25)]);
+    }
+
+    #[test]
+    fn add_returns_what_overflows_a_full_inventory() {
+        let kibble = item("kibble", 2);
+        let water = item("water", 5);
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.add(&kibble, 2 * INVENTORY_SLOTS as u32 - 2), 0);
+        assert_eq!(inventory.add(&water, 7), 2);
+        assert_eq!(inventory.count("water"), 5);
+        assert_eq!(inventory.add(&water, 1), 1);
+        assert_eq!(inventory.stacks.len(), INVENTORY_SLOTS);
+    }
+
+    #[test]
+    fn remove_takes_from_last_stack_and_drops_empty_ones() {
+        let kibble = item("kibble", 10);
+        let mut inventory = Inventory::default();
+        inventory.add(&kibble, 12);
+        assert_eq!(inventory.remove("kibble", 2), Ok(()));
+        assert_eq!(inventory.stacks.len(), 1);
+        assert_eq!(inventory.remove("kibble", 10), Ok(()));
+        assert!(inventory.stacks.is_empty());
+    }
+
+    #[test]
+    fn remove_more_than_owned_changes_nothing() {
+        let kibble = item("kibble", 10);
+        let mut inventory = Inventory::default();
+        inventory.add(&kibble, 3);
+        assert_eq!(
+            inventory.remove("kibble", 4),
+            Err(NotEnoughItems {
+                id: "kibble".to_string(),
+                owned: 3,
+            })
+        );
+        assert_eq!(inventory.count("kibble"), 3);
+        assert!(inventory.remove("ball", 1).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let mut inventory = Inventory::starter();
+        inventory.add(&item("ball", 1), 2);
+        let text = ron::to_string(&inventory).unwrap();
+        assert_eq!(ron::from_str::<Inventory>(&text).unwrap(), inventory);
+    }
+}
@@ -0,0 +1,382 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    app::AppExit,
+    asset::{Asset, AssetLoader, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::de::DeserializeOwned;
+
+use super::anim::{SpriteSheets, SPRITE_SHEETS_PATH};
+use super::game::PLAY_MENU_PATH;
+use super::growth::GROWTH_TABLE_PATH;
+use super::menu::MAIN_MENU_PATH;
+use super::pet::Species;
+use super::settings::ThemePreset;
+use super::shop::CATALOGUE_PATH;
+use super::theme::{theme_path, ThemeFile, UiTheme};
+use super::ui::{button_style, spawn_button, spawn_panel, spawn_screen, MenuFile};
+use super::{despawn_screen, GameState};
+
+// This plugin loads every asset the game needs while the splash screen is shown, then shows how
+// far along it is in `GameState::Loading` and only moves on to the menu once everything is in.
+// Files that can't be loaded are listed on an error screen instead
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_game_assets)
+            .add_system(loading_setup.in_schedule(OnEnter(GameState::Loading)))
+            .add_systems((track_loading, quit_button).in_set(OnUpdate(GameState::Loading)))
+            .add_systems(
+                (build_species_icons, despawn_screen::<OnLoadingScreen>)
+                    .in_schedule(OnExit(GameState::Loading)),
+            );
+    }
+}
+
+// Files needed by the game that no data file points to
+const DATA_FILES: [&str; 5] = [
+    CATALOGUE_PATH,
+    GROWTH_TABLE_PATH,
+    SPRITE_SHEETS_PATH,
+    MAIN_MENU_PATH,
+    PLAY_MENU_PATH,
+];
+
+const VENDOR_TEXTURE: &str = "textures/generic-rpg-vendor.png";
+
+// Loader of the data files written in RON, read as `T`. Every kind of file has its own
+// extension, like "sheets.ron", and optional values can be written without `Some(...)`
+pub struct RonLoader<T> {
+    extensions: &'static [&'static str],
+    asset: PhantomData<fn() -> T>,
+}
+
+impl<T> RonLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            asset: PhantomData,
+        }
+    }
+}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset: T = parse_ron(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+fn parse_ron<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ron::error::SpannedError> {
+    ron::Options::default()
+        .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+        .from_bytes(bytes)
+}
+
+// Whether the asset behind `handle` was loaded or changed on disk, so what is built from it can
+// be built again
+pub fn asset_changed<T: Asset>(
+    events: &mut EventReader<AssetEvent<T>>,
+    handle: &Handle<T>,
+) -> bool {
+    events.iter().any(|event| match event {
+        AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed } => {
+            changed == handle
+        }
+        AssetEvent::Removed { .. } => false,
+    })
+}
+
+// Handles of the assets loaded up front. Holding them keeps them loaded, so the screens can
+// spawn right away with what they need
+#[derive(Resource)]
+pub struct GameAssets {
+    pub vendor: Handle<Image>,
+    // First frame of the sprite sheet of every species, built once the sheets are loaded
+    species_icons: HashMap<Species, Handle<Image>>,
+    // Everything waited on before leaving the loading screen, including the above and the
+    // files the data files point to
+    required: Vec<HandleUntyped>,
+}
+
+impl GameAssets {
+    pub fn species_icon(&self, species: Species) -> Handle<Image> {
+        self.species_icons
+            .get(&species)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Start loading `path` and wait on it, unless it already is
+    fn require(&mut self, asset_server: &AssetServer, path: &str) {
+        let handle = asset_server.load_untyped(path);
+        if !self
+            .required
+            .iter()
+            .any(|required| required.id() == handle.id())
+        {
+            self.required.push(handle);
+        }
+    }
+}
+
+// Tag component used to tag entities added on the loading screen
+#[derive(Component)]
+struct OnLoadingScreen;
+
+#[derive(Component)]
+struct ProgressBar;
+
+#[derive(Component)]
+struct ProgressText;
+
+// Tag component used to mark the quit button of the error screen
+#[derive(Component)]
+struct QuitButton;
+
+fn load_game_assets(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<UiTheme>) {
+    let vendor = asset_server.load(VENDOR_TEXTURE);
+    let required = vec![vendor.clone_untyped(), theme.font.clone_untyped()];
+    let mut game_assets = GameAssets {
+        vendor,
+        species_icons: HashMap::new(),
+        required,
+    };
+    for species in Species::ALL {
+        game_assets.require(&asset_server, species.texture());
+    }
+    let theme_files = ThemePreset::ALL.map(theme_path);
+    for path in DATA_FILES
+        .iter()
+        .copied()
+        .chain(theme_files.iter().map(String::as_str))
+    {
+        game_assets.require(&asset_server, path);
+    }
+    commands.insert_resource(game_assets);
+}
+
+fn loading_setup(mut commands: Commands, theme: Res<UiTheme>) {
+    spawn_screen(&mut commands, OnLoadingScreen).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section("Loading...", theme.text_style(30.0)).with_style(Style {
+                margin: UiRect::all(Val::Px(20.0)),
+                ..default()
+            }),
+            ProgressText,
+        ));
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(300.0), Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: theme.button.into(),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                            ..default()
+                        },
+                        background_color: theme.good.into(),
+                        ..default()
+                    },
+                    ProgressBar,
+                ));
+            });
+    });
+}
+
+// Wait on the data files, then on the files they point to, and move on to the menu once all
+// of them are loaded
+#[allow(clippy::too_many_arguments)]
+fn track_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game_assets: ResMut<GameAssets>,
+    menus: Res<Assets<MenuFile>>,
+    sheets: Res<Assets<SpriteSheets>>,
+    themes: Res<Assets<ThemeFile>>,
+    theme: Res<UiTheme>,
+    screen: Query<Entity, With<OnLoadingScreen>>,
+    mut bars: Query<&mut Style, With<ProgressBar>>,
+    mut texts: Query<&mut Text, With<ProgressText>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut failed_shown: Local<bool>,
+) {
+    if *failed_shown {
+        return;
+    }
+    let icons = menus
+        .iter()
+        .flat_map(|(_, menu)| menu.0.items.iter().filter_map(|item| item.icon.clone()));
+    let textures = sheets
+        .iter()
+        .flat_map(|(_, sheets)| sheets.0.values().map(|sheet| sheet.texture.clone()));
+    let fonts = themes.iter().map(|(_, theme)| theme.0.font_path.clone());
+    let referenced: Vec<String> = icons.chain(textures).chain(fonts).collect();
+    for path in &referenced {
+        game_assets.require(&asset_server, path);
+    }
+
+    let mut loaded = 0;
+    let mut failed = Vec::new();
+    for handle in &game_assets.required {
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => failed.push(match asset_server.get_handle_path(handle) {
+                Some(path) => path.path().display().to_string(),
+                None => format!("{:?}", handle.id()),
+            }),
+            _ => {}
+        }
+    }
+    if !failed.is_empty() {
+        for path in &failed {
+            error!("Could not load {path}");
+        }
+        for entity in &screen {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_error_screen(&mut commands, &theme, &failed);
+        *failed_shown = true;
+        return;
+    }
+    let total = game_assets.required.len();
+    if loaded == total {
+        game_state.set(GameState::Menu);
+    }
+    for mut style in &mut bars {
+        style.size.width = Val::Percent(loaded as f32 / total as f32 * 100.0);
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = format!("Loading... {loaded}/{total}");
+    }
+}
+
+// Cut the icon of every species out of its sprite sheet, everything is loaded by now
+fn build_species_icons(
+    mut game_assets: ResMut<GameAssets>,
+    asset_server: Res<AssetServer>,
+    sheets: Res<Assets<SpriteSheets>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (_, sheets) in sheets.iter() {
+        for (species, sheet) in &sheets.0 {
+            let texture: Handle<Image> = asset_server.load(sheet.texture.as_str());
+            let Some(icon) = images
+                .get(&texture)
+                .map(|texture| sheet.first_frame(texture))
+            else {
+                continue;
+            };
+            game_assets.species_icons.insert(*species, images.add(icon));
+        }
+    }
+}
+
+// Tell which files are missing or broken, the game can't go on without them
+fn spawn_error_screen(commands: &mut Commands, theme: &UiTheme, failed: &[String]) {
+    spawn_screen(commands, OnLoadingScreen).with_children(|parent| {
+        spawn_panel(parent, theme.panel, 20.0).with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Some game files could not be loaded",
+                    TextStyle {
+                        color: theme.danger,
+                        ..theme.text_style(30.0)
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(15.0)),
+                    ..default()
+                }),
+            );
+            for path in failed {
+                parent.spawn(TextBundle::from_section(
+                    path.clone(),
+                    theme.text_style(20.0),
+                ));
+            }
+            parent.spawn(
+                TextBundle::from_section(
+                    "They are missing from the assets folder or are not valid",
+                    theme.text_style(20.0),
+                )
+                .with_style(Style {
+                    margin: UiRect::top(Val::Px(15.0)),
+                    ..default()
+                }),
+            );
+            spawn_button(
+                parent,
+                theme,
+                Style {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..button_style(250.0, 65.0, 0.0)
+                },
+                "Quit",
+                theme.text_style(25.0),
+                QuitButton,
+            );
+        });
+    });
+}
+
+fn quit_button(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<QuitButton>)>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        app_exit_events.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::growth::GrowthTable;
+    use crate::shop::ItemCatalogue;
+
+    fn parse_asset<T: DeserializeOwned>(path: &str) -> T {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join(path);
+        let bytes = std::fs::read(&path).unwrap();
+        parse_ron(&bytes).unwrap_or_else(|err| panic!("{}: {err}", path.display()))
+    }
+
+    #[test]
+    fn data_files_parse() {
+        parse_asset::<ItemCatalogue>(CATALOGUE_PATH);
+        parse_asset::<GrowthTable>(GROWTH_TABLE_PATH);
+        parse_asset::<SpriteSheets>(SPRITE_SHEETS_PATH);
+        parse_asset::<MenuFile>(MAIN_MENU_PATH);
+        parse_asset::<MenuFile>(PLAY_MENU_PATH);
+        for preset in ThemePreset::ALL {
+            parse_asset::<ThemeFile>(&theme_path(preset));
+        }
+    }
+}
@@ -5,13 +5,31 @@
 use bevy::prelude::*;
 use bevy::window::{Window, WindowPlugin, CompositeAlphaMode,WindowResolution};
 
-const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+mod actions;
+mod anim;
+mod growth;
+mod inventory;
+mod loading;
+mod market;
+mod navigation;
+mod pet;
+mod save;
+mod settings;
+mod shop;
+mod sickness;
+mod theme;
+mod ui;
+mod wander;
+mod widget;
+
 
 // Enum that will be used as a global state for the game
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum GameState {
     #[default]
     Splash,
+    // Waiting for the assets still loading when the splash screen is over
+    Loading,
     Menu,
     NewGame,
     Game,
@@ -25,28 +43,44 @@ fn main() {
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: ("Pet Fun").to_string(),
-                resolution: WindowResolution::new(800., 600.).with_scale_factor_override(1.0),
+                resolution: WindowResolution::new(widget::WINDOW_SIZE.x, widget::WINDOW_SIZE.y).with_scale_factor_override(1.0),
                 // Setting `transparent` allows the `ClearColor`'s alpha value to take effect
                 transparent: true,
-                // Disabling window decorations to make it feel more like a widget than a window
+                // Decorations are removed by the widget mode, to make it feel more like a widget than a window
                 decorations: true,
                 //#[cfg(target_os = "macos")]
                 //composite_alpha_mode: CompositeAlphaMode::PostMultiplied,
                 ..default()
             }),
             ..default()
+        }).set(AssetPlugin {
+            // Data and menu files are picked up again when they change on disk
+            watch_for_changes: true,
+            ..default()
         }))
         // Insert as resource the initial value for the settings resources
-        //.insert_resource(DisplayQuality::Medium)
-        //.insert_resource(Volume(7))
-        
+        .add_plugin(settings::SettingsPlugin)
         .add_startup_system(setup)
         // Declare the game state, whose starting value is determined by the `Default` trait
         .add_state::<GameState>()
         // Adds the plugins for each state
         .add_plugin(splash::SplashPlugin)
+        .add_plugin(loading::LoadingPlugin)
         .add_plugin(menu::MenuPlugin)
         .add_plugin(game::GamePlugin)
+        .add_plugin(pet::PetPlugin)
+        .add_plugin(anim::AnimationPlugin)
+        .add_plugin(growth::GrowthPlugin)
+        .add_plugin(save::SavePlugin)
+        .add_plugin(market::MarketPlugin)
+        .add_plugin(shop::ShopPlugin)
+        .add_plugin(inventory::InventoryPlugin)
+        .add_plugin(sickness::SicknessPlugin)
+        .add_plugin(widget::WidgetPlugin)
+        .add_plugin(wander::WanderPlugin)
+        .add_plugin(ui::UiPlugin)
+        .add_plugin(theme::ThemePlugin)
+        .add_plugin(navigation::NavigationPlugin)
         .run();
 }
 
@@ -55,11 +89,18 @@ fn setup(mut commands: Commands) {
 }
 
 mod splash {
+    use std::time::Duration;
+
     use bevy::prelude::*;
 
+    use super::actions::Actions;
+    use super::settings::ShowSplash;
+    use super::ui::spawn_screen;
     use super::{despawn_screen, GameState};
 
-    // This plugin will display a splash screen with Bevy logo for 1 second before switching to the menu
+    // This plugin will display a splash screen with Bevy logo before switching to the loading screen.
+    // The logo fades in, stays and fades out (`FADE_IN` + `HOLD` + `FADE_OUT`), any key or click skips
+    // to the fade out. It can be turned off in the settings or with `--no-splash`
     pub struct SplashPlugin;
 
     impl Plugin for SplashPlugin {
@@ -68,8 +109,13 @@ mod splash {
             app
                 // When entering the state, spawn everything needed for this screen
                 .add_system(splash_setup.in_schedule(OnEnter(GameState::Splash)))
-                // While in this state, run the `countdown` system
-                .add_system(countdown.in_set(OnUpdate(GameState::Splash)))
+                // While in this state, run the `countdown` system. There is no timer when the splash is turned off
+                .add_systems(
+                    (skip_splash, countdown)
+                        .chain()
+                        .in_set(OnUpdate(GameState::Splash))
+                        .distributive_run_if(resource_exists::<SplashTimer>()),
+                )
                 // When exiting the state, despawn everything that was spawned for this screen
                 .add_system(
                     despawn_screen::<OnSplashScreen>.in_schedule(OnExit(GameState::Splash)),
@@ -77,70 +123,139 @@ mod splash {
         }
     }
 
+    // Pass this on the command line to start without the splash screen
+    const NO_SPLASH_FLAG: &str = "--no-splash";
+
+    // How long the logo takes to fade in, stays fully shown and takes to fade out, in seconds
+    const FADE_IN: f32 = 0.4;
+    const HOLD: f32 = 1.0;
+    const FADE_OUT: f32 = 0.4;
+
     // Tag component used to tag entities added on the splash screen
     #[derive(Component)]
     struct OnSplashScreen;
 
+    // Tag component used to mark the logo that fades in and out
+    #[derive(Component)]
+    struct SplashLogo;
+
     // Newtype to use a `Timer` for this screen as a resource
     #[derive(Resource, Deref, DerefMut)]
     struct SplashTimer(Timer);
 
-    fn splash_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Opacity of the logo `elapsed` seconds into the splash
+    fn logo_alpha(elapsed: f32) -> f32 {
+        if elapsed < FADE_IN {
+            elapsed / FADE_IN
+        } else if elapsed < FADE_IN + HOLD {
+            1.0
+        } else {
+            (1.0 - (elapsed - FADE_IN - HOLD) / FADE_OUT).max(0.0)
+        }
+    }
+
+    fn splash_setup(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        show_splash: Res<ShowSplash>,
+        mut game_state: ResMut<NextState<GameState>>,
+    ) {
+        if !show_splash.0 || std::env::args().any(|arg| arg == NO_SPLASH_FLAG) {
+            game_state.set(GameState::Loading);
+            return;
+        }
         let icon = asset_server.load("branding/welcome-page.png");
         // Display the logo
-        commands
-            .spawn((
-                NodeBundle {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        ..default()
-                    },
-                    ..default()
-                },
-                OnSplashScreen,
-            ))
-            .with_children(|parent| {
-                parent.spawn(ImageBundle {
+        spawn_screen(&mut commands, OnSplashScreen).with_children(|parent| {
+            parent.spawn((
+                ImageBundle {
                     style: Style {
                         // This will set the logo to be 200px wide, and auto adjust its height
                         size: Size::new(Val::Px(512.0), Val::Auto),
                         ..default()
                     },
                     image: UiImage::new(icon),
+                    // Starts hidden and fades in
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.0).into(),
                     ..default()
-                });
-            });
+                },
+                SplashLogo,
+            ));
+        });
         // Insert the timer as a resource
-        commands.insert_resource(SplashTimer(Timer::from_seconds(1.0, TimerMode::Once)));
+        commands.insert_resource(SplashTimer(Timer::from_seconds(
+            FADE_IN + HOLD + FADE_OUT,
+            TimerMode::Once,
+        )));
+    }
+
+    // Any key, click or gamepad button starts the fade out right away, from where the fade in got to
+    fn skip_splash(actions: Actions, mut timer: ResMut<SplashTimer>) {
+        let elapsed = timer.elapsed_secs();
+        if elapsed < FADE_IN + HOLD && actions.any_just_pressed().is_some() {
+            let fade_out = FADE_IN + HOLD + FADE_OUT * (1.0 - logo_alpha(elapsed));
+            timer.set_elapsed(Duration::from_secs_f32(fade_out));
+        }
     }
 
-    // Tick the timer, and change state when finished
+    // Tick the timer, fade the logo and change state when finished
     fn countdown(
         mut game_state: ResMut<NextState<GameState>>,
         time: Res<Time>,
         mut timer: ResMut<SplashTimer>,
+        mut logos: Query<&mut BackgroundColor, With<SplashLogo>>,
     ) {
         if timer.tick(time.delta()).finished() {
-            game_state.set(GameState::Menu);
+            game_state.set(GameState::Loading);
+        }
+        for mut color in &mut logos {
+            color.0.set_a(logo_alpha(timer.elapsed_secs()));
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn logo_fades_in_and_out() {
+            assert_eq!(logo_alpha(0.0), 0.0);
+            assert_eq!(logo_alpha(FADE_IN / 2.0), 0.5);
+            // Fully shown from the end of the fade in to the start of the fade out
+            assert_eq!(logo_alpha(FADE_IN), 1.0);
+            assert_eq!(logo_alpha(FADE_IN + HOLD - 0.01), 1.0);
+            assert_eq!(logo_alpha(FADE_IN + HOLD), 1.0);
+            assert!((logo_alpha(FADE_IN + HOLD + FADE_OUT / 2.0) - 0.5).abs() < 1e-5);
+            assert!(logo_alpha(FADE_IN + HOLD + FADE_OUT) < 1e-5);
+            // Never goes below zero, even if the timer overshoots
+            assert_eq!(logo_alpha(FADE_IN + HOLD + FADE_OUT + 1.0), 0.0);
         }
     }
 }
 
 mod game {
-    use super::{despawn_screen, GameState,  TEXT_COLOR};
+    use super::{despawn_screen, GameState};
+    use super::inventory::{Inventory, ItemRegistry};
+    use super::actions::{Action, Actions, Binding};
+    use super::pet::{Pet, PetCommand, PetFeedback, PetPicker};
+    use super::shop::ItemKind;
+    use super::theme::UiTheme;
+    use super::loading::asset_changed;
+    use super::ui::{spawn_button, spawn_menu, spawn_panel, spawn_screen, button_style, MenuFile};
+    use super::widget::fit_window;
+    use serde::Deserialize;
    // #[cfg(target_os = "macos")]
     use bevy::{
         prelude::*,
+        window::PrimaryWindow,
     };
-    // This plugin will contain the game. In this case, it's just be a screen that will
-    // display the current settings for 5 seconds before returning to the menu
+    // This plugin shows the pet while playing, and the play, feed and care menus next to it
     
     #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-    enum PlayMenuState {
+    pub enum PlayMenuState {
         Show,
         FeedMenu,
+        CareMenu,
         #[default]
         Disable,
     }
@@ -151,18 +266,34 @@ mod game {
         
         fn build(&self, app: &mut App ) {
             app.add_state::<PlayMenuState>()
+            .init_resource::<PlayMenuAnchor>()
+            .add_startup_system(load_play_menu)
+            .add_system(reload_play_menu)
             .add_systems((  
                 game_setup.in_schedule(OnEnter(GameState::Game)),
-                
-                //game.in_set(OnUpdate(GameState::Game)),
+                show_feedback.in_set(OnUpdate(GameState::Game)),
+                hide_pet.in_schedule(OnExit(GameState::Game)),
+                reset_play_menu.in_schedule(OnExit(GameState::Game)),
                 despawn_screen::<OnGameScreen>.in_schedule(OnExit(GameState::Game)),
             ))
             .add_systems((
                 play_menu_show.run_if(in_state(GameState::Game)),
+                close_play_menu.run_if(in_state(GameState::Game)).after(play_menu_show),
                 play_menu_setup.in_schedule(OnEnter(PlayMenuState::Show)),
+                place_play_menu.after(fit_window).in_set(OnUpdate(PlayMenuState::Show)),
                 despawn_screen::<OnPlayMenuScreen>.in_schedule(OnExit(PlayMenuState::Show)),
             ))
-            .add_systems((play_menu_action, button_system).in_set(OnUpdate(PlayMenuState::Show)));
+            .add_system(play_menu_action.in_set(OnUpdate(PlayMenuState::Show)))
+            .add_systems((
+                feed_menu_setup.in_schedule(OnEnter(PlayMenuState::FeedMenu)),
+                despawn_screen::<OnFeedMenuScreen>.in_schedule(OnExit(PlayMenuState::FeedMenu)),
+            ))
+            .add_system(feed_menu_action.in_set(OnUpdate(PlayMenuState::FeedMenu)))
+            .add_systems((
+                care_menu_setup.in_schedule(OnEnter(PlayMenuState::CareMenu)),
+                despawn_screen::<OnCareMenuScreen>.in_schedule(OnExit(PlayMenuState::CareMenu)),
+            ))
+            .add_system(feed_menu_action.in_set(OnUpdate(PlayMenuState::CareMenu)));
        
         }
     }
@@ -175,225 +306,284 @@ mod game {
     #[derive(Component)]
     struct OnPlayMenuScreen;
 
-    #[derive(Resource, Deref, DerefMut)]
-    struct GameTimer(Timer);
+    // Tag component used to tag entities added on the feed menu screen
+    #[derive(Component)]
+    struct OnFeedMenuScreen;
 
+    // Tag component used to tag entities added on the care menu screen
+    #[derive(Component)]
+    struct OnCareMenuScreen;
+
+    // Panel of the play, feed and care menus. Clicking anywhere else closes the menu
+    #[derive(Component)]
+    struct PlayMenuPanel;
 
-    const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-    const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-    const HOVERED_PRESSED_BUTTON: Color = Color::rgb(0.25, 0.65, 0.25);
-    const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+    // Where the pet was (in world coordinates) when the play menu was opened, the bar is put next
+    // to the pet
+    #[derive(Resource, Default)]
+    struct PlayMenuAnchor {
+        pet: Rect,
+    }
 
+    // Bar of the play menu and its size. It is placed again whenever the window is resized, like
+    // when the widget window grows to fit the menu
     #[derive(Component)]
+    struct PlayMenuBar(Vec2);
+
+    // Space left between the play menu bar and the pet
+    const PLAY_MENU_GAP: f32 = 8.0;
+
+    // Buttons of the play menu bar, from `assets/ui/play.menu.ron`
+    #[derive(Resource, Deref)]
+    struct PlayMenuFile(Handle<MenuFile>);
+
+    // Message shown over the pet, despawned when its timer runs out
+    #[derive(Component, Deref, DerefMut)]
+    struct Toast(Timer);
+
+
+    #[derive(Component, Clone, Deserialize)]
     enum PlayMenuButtonAction {
         //PlayMenu,
         FeedMenu,
+        CareMenu, //Clean or heal the pet
+        PlayWithPet, //Play a game with the pet
         WakeUpPet, //Wake up pet
         SleepPet,//Make pet sleep
         IdlePet, //Make pet into idle situation
         BackToMain,
     }
-    
-    fn game_setup(mut commands: Commands,asset_server: Res<AssetServer>,) {
-    
-        commands
-        .spawn((
-            SpriteBundle{
-                texture: asset_server.load("textures/turtle-1.png"),
-                ..default()
-            },
-            OnGameScreen,
-        ));
-            
-        // Spawn a 5 seconds timer to trigger going back to the menu
-        commands.insert_resource(GameTimer(Timer::from_seconds(5.0, TimerMode::Once)));
-        
 
+    // Actions of the feed and care menus, `Eat` and `Use` hold the id of the chosen item
+    #[derive(Component)]
+    enum FeedMenuButtonAction {
+        Eat(String),
+        Use(String),
+        BackToPlayMenu,
     }
-
-    // Tick the timer, and change state when finished
-    fn game(
-        time: Res<Time>,
+    
+    // The pet outlives the game screen (it is only hidden while in the menus), it is spawned
+    // when a save is loaded, a new game is started or a pet is bought
+    fn game_setup(
         mut game_state: ResMut<NextState<GameState>>,
-        mut timer: ResMut<GameTimer>,
+        mut pets: Query<&mut Visibility, With<Pet>>,
     ) {
-        if timer.tick(time.delta()).finished() {
+        // There is nothing to play with once the pet was sold
+        if pets.is_empty() {
             game_state.set(GameState::Menu);
         }
+        for mut visibility in &mut pets {
+            *visibility = Visibility::Inherited;
+        }
+    }
+
+    fn hide_pet(mut pets: Query<&mut Visibility, With<Pet>>) {
+        for mut visibility in &mut pets {
+            *visibility = Visibility::Hidden;
+        }
     }
-    // Tag component used to mark which setting is currently selected
-    #[derive(Component)]
-    struct SelectedOption;
 
-    // This system handles changing all buttons color based on mouse interaction
-    fn button_system(
-        mut interaction_query: Query<(&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
-            (Changed<Interaction>, With<Button>),
-        >,
+    // Show what the pet has to say (like a refused command) for 2 seconds
+    fn show_feedback(
+        mut commands: Commands,
+        theme: Res<UiTheme>,
+        time: Res<Time>,
+        mut feedback: EventReader<PetFeedback>,
+        mut toasts: Query<(Entity, &mut Toast)>,
     ) {
-        for (interaction, mut color, selected) in &mut interaction_query {
-            *color = match (*interaction, selected) {
-                (Interaction::Clicked, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
-                (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
-                (Interaction::Hovered, None) => HOVERED_BUTTON.into(),
-                (Interaction::None, None) => NORMAL_BUTTON.into(),
+        for (entity, mut timer) in &mut toasts {
+            if timer.tick(time.delta()).finished() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        // Only the latest message is worth showing
+        if let Some(PetFeedback(message)) = feedback.iter().last() {
+            for (entity, _) in &toasts {
+                commands.entity(entity).despawn_recursive();
             }
+            commands.spawn((
+                TextBundle::from_section(message.clone(), theme.text_style(22.0))
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(20.0),
+                        top: Val::Px(20.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_background_color(theme.button),
+                Toast(Timer::from_seconds(2.0, TimerMode::Once)),
+                OnGameScreen,
+            ));
         }
-    } 
+    }
+
  
+    // Right-clicking the pet opens the play menu next to it, and closes it when it is open.
+    // Keys and gamepad buttons bound to the action work wherever the cursor is
     fn play_menu_show(  
-        mouse_button_input: Res<Input<MouseButton>>,
+        actions: Actions,
+        play_menu_state: Res<State<PlayMenuState>>,
         mut menu_state: ResMut<NextState<PlayMenuState>>,
+        picker: PetPicker,
+        mut anchor: ResMut<PlayMenuAnchor>,
         //game_state: ResMut<State<GameState>>,
     ) {          
-                         
-                if mouse_button_input.pressed(MouseButton::Right){
-                    menu_state.set(PlayMenuState::Show);
-                }
-
-            
-                
-                    
+        let Some(binding) = actions.just_pressed_by(Action::OpenPetMenu) else {
+            return;
+        };
+        if matches!(binding, Binding::Mouse(_)) && !picker.cursor_on_pet() {
+            return;
+        }
+        if play_menu_state.0 != PlayMenuState::Disable {
+            menu_state.set(PlayMenuState::Disable);
+            return;
+        }
+        let Some(pet) = picker.pet_rect() else {
+            return;
+        };
+        anchor.pet = pet;
+        menu_state.set(PlayMenuState::Show);
     }   
 
-   fn play_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    // Common style for all buttons on the screen
-    let button_style = Style {
-        size: Size::new(Val::Px(86.0), Val::Px(40.0)),
-        margin: UiRect{
-            left: Val::Px(7.0),
-            right: Val::Px(7.0),
-            top: Val::Px(8.0),
-            bottom: Val::Px(8.0)
-        },
-        justify_content: JustifyContent::Center,
-        align_items: AlignItems::Center,
-        ..default()
-    };
-    let button_icon_style = Style {
-        size: Size::new(Val::Px(30.0), Val::Auto),
-        // This takes the icons out of the flexbox flow, to be positioned exactly
-        position_type: PositionType::Absolute,
-        // The icon will be close to the left border of the button
-        position: UiRect {
-            left: Val::Px(10.0),
-            right: Val::Auto,
-            top: Val::Auto,
-            bottom: Val::Auto,
-        },
-        ..default()
-    };
-    let button_text_style = TextStyle {
-        font: font.clone(),
-        font_size: 25.0,
-        color: TEXT_COLOR,
-    };
+    // Where to put a play menu bar of `size` for a pet covering `pet` (in world coordinates): under
+    // the pet, or over it when there is no room left under it, and always inside the window
+    fn play_menu_anchor(pet: Rect, window_size: Vec2, size: Vec2) -> Vec2 {
+        // The camera is in the middle of the window, UI coordinates go down from the top left
+        let to_ui = |world: Vec2| Vec2::new(world.x + window_size.x / 2.0, window_size.y / 2.0 - world.y);
+        let (top, bottom) = (to_ui(pet.max), to_ui(pet.min));
+        let y = if bottom.y + PLAY_MENU_GAP + size.y <= window_size.y {
+            bottom.y + PLAY_MENU_GAP
+        } else {
+            top.y - PLAY_MENU_GAP - size.y
+        };
+        let x = (top.x + bottom.x) / 2.0 - size.x / 2.0;
+        let max = (window_size - size).max(Vec2::ZERO);
+        Vec2::new(x, y).clamp(Vec2::ZERO, max)
+    }
+
+    // The menu doesn't stay open over the main menu when the game screen is left
+    fn reset_play_menu(mut menu_state: ResMut<NextState<PlayMenuState>>) {
+        menu_state.set(PlayMenuState::Disable);
+    }
 
+    // Cancelling goes back one menu, to the main menu when none is open. Clicking away from the
+    // open menu closes it
+    fn close_play_menu(
+        actions: Actions,
+        mouse_button_input: Res<Input<MouseButton>>,
+        play_menu_state: Res<State<PlayMenuState>>,
+        mut menu_state: ResMut<NextState<PlayMenuState>>,
+        mut game_state: ResMut<NextState<GameState>>,
+        picker: PetPicker,
+        menu_nodes: Query<&Interaction, Or<(With<PlayMenuPanel>, With<Button>)>>,
+    ) {
+        if actions.just_pressed(Action::Cancel) {
+            match play_menu_state.0 {
+                PlayMenuState::FeedMenu | PlayMenuState::CareMenu => menu_state.set(PlayMenuState::Show),
+                PlayMenuState::Show => menu_state.set(PlayMenuState::Disable),
+                PlayMenuState::Disable => game_state.set(GameState::Menu),
+            }
+            return;
+        }
+        if play_menu_state.0 == PlayMenuState::Disable {
+            return;
+        }
+        let clicked = mouse_button_input.any_just_pressed([MouseButton::Left, MouseButton::Right]);
+        let on_menu = menu_nodes.iter().any(|interaction| *interaction != Interaction::None);
+        // Clicks on the pet are left to `play_menu_show` and the dragging of the widget
+        if clicked && !on_menu && !picker.cursor_on_pet() {
+            menu_state.set(PlayMenuState::Disable);
+        }
+    }
+
+    pub const PLAY_MENU_PATH: &str = "ui/play.menu.ron";
+
+    fn load_play_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(PlayMenuFile(asset_server.load(PLAY_MENU_PATH)));
+    }
+
+    // Open the menu again when the play menu file or the theme changes, to show the new buttons
+    // and colours
+    fn reload_play_menu(
+        mut events: EventReader<AssetEvent<MenuFile>>,
+        play_menu_file: Res<PlayMenuFile>,
+        theme: Res<UiTheme>,
+        play_menu_state: Res<State<PlayMenuState>>,
+        mut menu_state: ResMut<NextState<PlayMenuState>>,
+    ) {
+        let buttons_changed = asset_changed(&mut events, &play_menu_file) && play_menu_state.0 == PlayMenuState::Show;
+        let restyled = theme.is_changed() && !theme.is_added() && play_menu_state.0 != PlayMenuState::Disable;
+        if buttons_changed || restyled {
+            menu_state.set(play_menu_state.0);
+        }
+    }
+
+    fn play_menu_setup(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        theme: Res<UiTheme>,
+        menu_files: Res<Assets<MenuFile>>,
+        play_menu_file: Res<PlayMenuFile>,
+    ) {
+        // Nothing to show until the file is loaded, the menu is opened again then
+        let Some(menu) = menu_files.get(&play_menu_file) else {
+            return;
+        };
+        let menu = menu.menu::<PlayMenuButtonAction>();
         commands
             .spawn((
                 NodeBundle {
                     style: Style {
                         size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
                         ..default()
                     },
                     ..default()
                 },
                 OnPlayMenuScreen,
             ))
-            //Show five botton
-            //Feed
-            //Game
-            //Wake or sleep
-            //Idle
-            //Back to main
             .with_children(|parent| {
+                // The bar is put next to the pet by `place_play_menu`
                 parent
-                    .spawn(NodeBundle{
+                    .spawn((NodeBundle{
                         style:Style { 
-                            
-                            flex_direction: FlexDirection::Row, 
-                            position: UiRect{left:Val::Px(0.),top:Val::Px(142.),..default()},
-                            size: Size{width:Val::Px(500.),height:Val::Px(56.)},
+                            position_type: PositionType::Absolute,
                             ..default()
                         },
-                        
-                        background_color: Color::ORANGE.into(),
                          ..default()
-                    })
-                    .with_children(|parent| {       
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                PlayMenuButtonAction::BackToMain,
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(TextBundle::from_section("Feed",button_text_style.clone(),));
-                            });
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                PlayMenuButtonAction::BackToMain,
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(TextBundle::from_section("Wake Up",button_text_style.clone(),));
-                            });
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                PlayMenuButtonAction::BackToMain,
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(TextBundle::from_section("Sleep",button_text_style.clone(),));
-                            });
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                PlayMenuButtonAction::BackToMain,
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(TextBundle::from_section("Idle",button_text_style.clone(),));
-                            });
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                PlayMenuButtonAction::BackToMain,
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(TextBundle::from_section("Back",button_text_style.clone(),));
-                            });
+                    }, PlayMenuBar(menu.size()), PlayMenuPanel, Interaction::default()))
+                    .with_children(|parent| {
+                        spawn_menu(parent, &asset_server, &theme, &menu, |_| true);
                     });
-                   
             });     
-            
     }
     
+    // Keep the bar next to the pet in the window as it is now, it runs after the widget window is
+    // resized for the menu
+    fn place_play_menu(
+        anchor: Res<PlayMenuAnchor>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+        mut bars: Query<(&PlayMenuBar, &mut Style)>,
+    ) {
+        let Ok(window) = windows.get_single() else {
+            return;
+        };
+        let window_size = Vec2::new(window.width(), window.height());
+        for (bar, mut style) in &mut bars {
+            let position = play_menu_anchor(anchor.pet, window_size, bar.0);
+            let position = UiRect { left: Val::Px(position.x), top: Val::Px(position.y), ..default() };
+            // Only touch the style when the bar moves, setting it lays the UI out again
+            if style.position != position {
+                style.position = position;
+            }
+        }
+    }
+
     fn play_menu_action(interaction_query: Query<(&Interaction, &PlayMenuButtonAction),(Changed<Interaction>, With<Button>),>,
     mut play_menu_state: ResMut<NextState<PlayMenuState>>,
         mut game_state: ResMut<NextState<GameState>>,
-        
+        mut pet_commands: EventWriter<PetCommand>,
     ) {
   
         for (interaction, menu_button_action) in &interaction_query {
@@ -401,9 +591,23 @@ mod game {
                 match menu_button_action {
                    // PlayMenuButtonAction::Quit => app_exit_events.send(AppExit),
                     PlayMenuButtonAction::FeedMenu => play_menu_state.set(PlayMenuState::FeedMenu),
-                    PlayMenuButtonAction::WakeUpPet => play_menu_state.set(PlayMenuState::FeedMenu),
-                    PlayMenuButtonAction::SleepPet => play_menu_state.set(PlayMenuState::FeedMenu),
-                    PlayMenuButtonAction::IdlePet => play_menu_state.set(PlayMenuState::FeedMenu),
+                    PlayMenuButtonAction::CareMenu => play_menu_state.set(PlayMenuState::CareMenu),
+                    PlayMenuButtonAction::PlayWithPet => {
+                        pet_commands.send(PetCommand::Play);
+                        play_menu_state.set(PlayMenuState::Disable);
+                    }
+                    PlayMenuButtonAction::WakeUpPet => {
+                        pet_commands.send(PetCommand::Wake);
+                        play_menu_state.set(PlayMenuState::Disable);
+                    }
+                    PlayMenuButtonAction::SleepPet => {
+                        pet_commands.send(PetCommand::Sleep);
+                        play_menu_state.set(PlayMenuState::Disable);
+                    }
+                    PlayMenuButtonAction::IdlePet => {
+                        pet_commands.send(PetCommand::Idle);
+                        play_menu_state.set(PlayMenuState::Disable);
+                    }
                     PlayMenuButtonAction::BackToMain => {
                         
                         
@@ -418,15 +622,190 @@ mod game {
  
     }
 
+    fn feed_menu_setup(
+        mut commands: Commands,
+        theme: Res<UiTheme>,
+        inventory: Res<Inventory>,
+        registry: Res<ItemRegistry>,
+    ) {
+        spawn_item_menu(
+            &mut commands,
+            &theme,
+            &inventory,
+            &registry,
+            OnFeedMenuScreen,
+            &[ItemKind::Food, ItemKind::Drink],
+            "Nothing to eat! Buy some food at the shop",
+            FeedMenuButtonAction::Eat,
+        );
+    }
+
+    fn care_menu_setup(
+        mut commands: Commands,
+        theme: Res<UiTheme>,
+        inventory: Res<Inventory>,
+        registry: Res<ItemRegistry>,
+    ) {
+        spawn_item_menu(
+            &mut commands,
+            &theme,
+            &inventory,
+            &registry,
+            OnCareMenuScreen,
+            &[ItemKind::Care, ItemKind::Medicine],
+            "Nothing to care for the pet with! Buy some at the shop",
+            FeedMenuButtonAction::Use,
+        );
+    }
+
+    // Menu with one row per owned item of the given kinds: the button to use it, how many are
+    // left and what it does
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_item_menu(
+        commands: &mut Commands,
+        theme: &UiTheme,
+        inventory: &Inventory,
+        registry: &ItemRegistry,
+        screen: impl Component,
+        kinds: &[ItemKind],
+        empty_text: &str,
+        action: fn(String) -> FeedMenuButtonAction,
+    ) {
+        let button_style = button_style(120.0, 40.0, 6.0);
+        let button_text_style = theme.text_style(25.0);
+        let effect_text_style = theme.text_style(20.0);
+
+        spawn_screen(commands, screen).with_children(|parent| {
+            spawn_panel(parent, theme.bar, 8.0)
+                .insert((PlayMenuPanel, Interaction::default()))
+                .with_children(|parent| {
+                    let items: Vec<_> = inventory
+                        .items()
+                        .into_iter()
+                        .filter_map(|(id, quantity)| Some((registry.get(id)?, quantity)))
+                        .filter(|(item, _)| kinds.contains(&item.kind))
+                        .collect();
+                    if items.is_empty() {
+                        parent.spawn(TextBundle::from_section(
+                            empty_text,
+                            effect_text_style.clone(),
+                        ));
+                    }
+                    for (item, quantity) in items {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Row,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                spawn_button(
+                                    parent,
+                                    theme,
+                                    button_style.clone(),
+                                    item.name.clone(),
+                                    button_text_style.clone(),
+                                    action(item.id.clone()),
+                                );
+                                parent.spawn(TextBundle::from_section(
+                                    format!("x{quantity}  {}", item.effect.summary()),
+                                    effect_text_style.clone(),
+                                ));
+                            });
+                    }
+                    spawn_button(parent, theme, button_style, "Back", button_text_style, FeedMenuButtonAction::BackToPlayMenu);
+                });
+        });
+    }
+
+    fn feed_menu_action(
+        interaction_query: Query<(&Interaction, &FeedMenuButtonAction), (Changed<Interaction>, With<Button>)>,
+        mut play_menu_state: ResMut<NextState<PlayMenuState>>,
+        mut pet_commands: EventWriter<PetCommand>,
+    ) {
+        for (interaction, feed_button_action) in &interaction_query {
+            if *interaction == Interaction::Clicked {
+                match feed_button_action {
+                    FeedMenuButtonAction::Eat(id) => {
+                        pet_commands.send(PetCommand::Feed(id.clone()));
+                        play_menu_state.set(PlayMenuState::Disable);
+                    }
+                    FeedMenuButtonAction::Use(id) => {
+                        pet_commands.send(PetCommand::Treat(id.clone()));
+                        play_menu_state.set(PlayMenuState::Disable);
+                    }
+                    FeedMenuButtonAction::BackToPlayMenu => play_menu_state.set(PlayMenuState::Show),
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const WINDOW: Vec2 = Vec2::new(800.0, 600.0);
+        const BAR: Vec2 = Vec2::new(300.0, 50.0);
+
+        #[test]
+        fn bar_goes_under_the_pet() {
+            let pet = Rect::from_center_size(Vec2::ZERO, Vec2::splat(64.0));
+            let position = play_menu_anchor(pet, WINDOW, BAR);
+            // The pet's bottom is at 332 in UI coordinates
+            assert_eq!(position, Vec2::new(250.0, 332.0 + PLAY_MENU_GAP));
+        }
+
+        #[test]
+        fn bar_goes_over_the_pet_at_the_bottom() {
+            let pet = Rect::from_center_size(Vec2::new(0.0, -250.0), Vec2::splat(64.0));
+            let position = play_menu_anchor(pet, WINDOW, BAR);
+            // The pet's top is at 518 in UI coordinates
+            assert_eq!(position.y, 518.0 - PLAY_MENU_GAP - BAR.y);
+        }
+
+        #[test]
+        fn bar_stays_in_the_window() {
+            let pet = Rect::from_center_size(Vec2::new(-390.0, 0.0), Vec2::splat(64.0));
+            assert_eq!(play_menu_anchor(pet, WINDOW, BAR).x, 0.0);
+            let pet = Rect::from_center_size(Vec2::new(390.0, 0.0), Vec2::splat(64.0));
+            assert_eq!(play_menu_anchor(pet, WINDOW, BAR).x, WINDOW.x - BAR.x);
+        }
+
+        #[test]
+        fn bar_wider_than_the_window_starts_at_its_left() {
+            let pet = Rect::from_center_size(Vec2::ZERO, Vec2::splat(64.0));
+            let position = play_menu_anchor(pet, Vec2::new(96.0, 96.0), BAR);
+            assert_eq!(position.x, 0.0);
+        }
+    }
 }
 
 
 
 
 mod menu {
-    use bevy::{app::AppExit, prelude::*};
+    use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+    use serde::Deserialize;
 
-    use super::{despawn_screen,  GameState,  TEXT_COLOR};
+    use super::{despawn_screen,  GameState};
+    use super::growth::Growth;
+    use super::market::{self, Ledger, TransactionKind, Wallet};
+    use super::pet::{spawn_pet, Pet, PetAge, PetProfile, PetStats, Species, Stat, STAT_MAX};
+    use super::save::{self, AwaySummary, GameInProgress, PetSave, Resumed, SaveLoader};
+    use super::actions::{Action, Actions, Binding, InputBindings, Rebinding};
+    use super::theme::UiTheme;
+    use super::ui::{
+        button_bundle, button_style, spawn_button, spawn_menu, spawn_panel, spawn_screen,
+        MenuFile, SelectedOption,
+    };
+    use super::settings::{AlwaysOnTop, DisplayQuality, ShowSplash, ThemePreset, Volume, WidgetMode, WindowScale};
+    use super::inventory::Inventory;
+    use super::loading::{asset_changed, GameAssets};
+    use super::shop::{CatalogueHandle, ItemCatalogue};
+    use super::sickness::{Fate, Graveyard, MemorialPending, Sickness};
 
     // This plugin manages the menu, with 5 different screens:
     // - a main menu with "New Game", "Settings", "Quit"
@@ -442,6 +821,8 @@ mod menu {
                 // Current screen in the menu is handled by an independent state from `GameState`
                 .add_state::<MenuState>()
                 .add_system(menu_setup.in_schedule(OnEnter(GameState::Menu)))
+                .add_startup_system(load_main_menu)
+                .add_system(reload_main_menu)
                 // Systems to handle the main menu screen
                 .add_systems((
                     main_menu_setup.in_schedule(OnEnter(MenuState::Main)),
@@ -451,30 +832,63 @@ mod menu {
                 // Systems to handle the new game menu screen
                 .add_systems((
                     new_game_setup.in_schedule(OnEnter(MenuState::NewGame)),
+                    setting_button::<ChosenSpecies>.in_set(OnUpdate(MenuState::NewGame)),
+                    pet_name_input.in_set(OnUpdate(MenuState::NewGame)),
                     despawn_screen::<OnNewGameScreen>.in_schedule(OnExit(MenuState::NewGame)),
                 ))                
-                // Systems to handle the continue play menu screen
-                /* 
+                // Systems to handle the pet status screen
+                .add_systems((
+                    update_setup.in_schedule(OnEnter(MenuState::Update)),
+                    update_stat_bars.in_set(OnUpdate(MenuState::Update)),
+                    despawn_screen::<OnUpdateScreen>.in_schedule(OnExit(MenuState::Update)),
+                ))
+                // Systems to handle the "while you were away" screen
+                .add_systems((
+                    away_setup.in_schedule(OnEnter(MenuState::Away)),
+                    despawn_screen::<OnAwayScreen>.in_schedule(OnExit(MenuState::Away)),
+                ))
+                // Systems to handle the memorial of a pet that died or ran away
                 .add_systems((
-                    play_menu_setup.in_schedule(OnEnter(MenuState::PlayMenu)),
-                    despawn_screen::<OnPlayMenuScreen>.in_schedule(OnExit(MenuState::PlayMenu)),
+                    open_memorial
+                        .run_if(resource_exists::<MemorialPending>())
+                        .in_set(OnUpdate(GameState::Menu)),
+                    memorial_setup.in_schedule(OnEnter(MenuState::Memorial)),
+                    despawn_screen::<OnMemorialScreen>.in_schedule(OnExit(MenuState::Memorial)),
                 ))
-                // Systems to handle the feed menu screen
+                // Systems to handle the transaction screen
                 .add_systems((
-                    feed_menu_setup.in_schedule(OnEnter(MenuState::FeedMenu)),
-                    //setting_button::<DisplayQuality>.in_set(OnUpdate(MenuState::SettingsDisplay)),
-                    //setting_button::<Volume>.in_set(OnUpdate(MenuState::SettingsSound)),
-                    despawn_screen::<OnFeedMenuScreen>.in_schedule(OnExit(MenuState::FeedMenu)),
+                    transaction_setup.in_schedule(OnEnter(MenuState::Transaction)),
+                    transaction_action.in_set(OnUpdate(MenuState::Transaction)),
+                    despawn_screen::<OnTransactionScreen>.in_schedule(OnExit(MenuState::Transaction)),
+                ))
+                // Systems to handle the vendor shop screen
+                .add_systems((
+                    shop_setup.in_schedule(OnEnter(MenuState::Shop)),
+                    shop_action.in_set(OnUpdate(MenuState::Shop)),
+                    despawn_screen::<OnShopScreen>.in_schedule(OnExit(MenuState::Shop)),
                 ))
-                */
                 // Systems to handle the settings menu screen
                 .add_systems((
                     settings_menu_setup.in_schedule(OnEnter(MenuState::Settings)),
+                    setting_button::<DisplayQuality>.in_set(OnUpdate(MenuState::Settings)),
+                    setting_button::<Volume>.in_set(OnUpdate(MenuState::Settings)),
+                    setting_button::<WindowScale>.in_set(OnUpdate(MenuState::Settings)),
+                    setting_button::<AlwaysOnTop>.in_set(OnUpdate(MenuState::Settings)),
+                    setting_button::<WidgetMode>.in_set(OnUpdate(MenuState::Settings)),
+                    setting_button::<ThemePreset>.in_set(OnUpdate(MenuState::Settings)),
+                    setting_button::<ShowSplash>.in_set(OnUpdate(MenuState::Settings)),
                     despawn_screen::<OnSettingsMenuScreen>.in_schedule(OnExit(MenuState::Settings)),
                 ))
+                // Systems to handle the controls screen
+                .add_systems((
+                    controls_menu_setup.in_schedule(OnEnter(MenuState::Controls)),
+                    rebind_action.in_set(OnUpdate(MenuState::Controls)),
+                    stop_rebinding.in_schedule(OnExit(MenuState::Controls)),
+                    despawn_screen::<OnControlsScreen>.in_schedule(OnExit(MenuState::Controls)),
+                ))
 
                 // Common systems to all screens that handles buttons behaviour
-                .add_systems((menu_action, button_system).in_set(OnUpdate(GameState::Menu)));
+                .add_systems((menu_action, menu_back).in_set(OnUpdate(GameState::Menu)));
         }
     }
 
@@ -483,11 +897,13 @@ mod menu {
     enum MenuState {
         Main,
         NewGame,
-        PlayMenu,
-        FeedMenu,
         Settings,
+        Controls,
         Update,
-        Transcation,
+        Transaction,
+        Shop,
+        Away,
+        Memorial,
         #[default]
         Disabled,
     }
@@ -500,18 +916,14 @@ mod menu {
     #[derive(Component)]
     struct OnNewGameScreen;
 
-    // Tag component used to tag entities added on the play menu screen
-    #[derive(Component)]
-    struct OnPlayMenuScreen;
-
-    // Tag component used to tag entities added on the feed menu screen
-    #[derive(Component)]
-    struct OnFeedMenuScreen;
-
     // Tag component used to tag entities added on the settings menu screen
     #[derive(Component)]
     struct OnSettingsMenuScreen;
 
+    // Tag component used to tag entities added on the controls screen
+    #[derive(Component)]
+    struct OnControlsScreen;
+
     // Tag component used to tag entities added on the update screen
     #[derive(Component)]
     struct OnUpdateScreen;
@@ -520,268 +932,1113 @@ mod menu {
     #[derive(Component)]
     struct OnTransactionScreen;
 
-    const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-    const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-    const HOVERED_PRESSED_BUTTON: Color = Color::rgb(0.25, 0.65, 0.25);
-    const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+    // Species picked on the new game screen, also used on the species buttons
+    #[derive(Resource, Component, Clone, Copy, Default, PartialEq, Debug)]
+    struct ChosenSpecies(Species);
+
+    // Shown under the main menu when the save couldn't give back a pet to continue with
+    #[derive(Resource)]
+    struct MenuNotice(String);
+
+    // Name typed on the new game screen
+    #[derive(Resource, Default)]
+    struct PetNameInput {
+        name: String,
+        // Set once the player was warned that starting will overwrite the current save
+        overwrite_confirmed: bool,
+    }
+
+    const MAX_PET_NAME_LEN: usize = 16;
 
-    // Tag component used to mark which setting is currently selected
+    // Tag component used to mark the text showing the typed pet name
     #[derive(Component)]
-    struct SelectedOption;
+    struct PetNameText;
 
-    // All actions that can be triggered from a button click
+    // Tag component used to mark the text warning about overwriting the save
     #[derive(Component)]
-    enum MenuButtonAction {
-        NewGame, //Create a new Game
-        ContinueGame, //Continue the Game
-        Settings,//Game settings
-        Update, //Pet state update
-        Transaction,//Buy or Sell pet
-        BackToMainMenu,    
-        Quit,
-    }
+    struct OverwriteWarningText;
 
-    // This system handles changing all buttons color based on mouse interaction
-    fn button_system(
-        mut interaction_query: Query<(&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
-            (Changed<Interaction>, With<Button>),
-        >,
-    ) {
-        for (interaction, mut color, selected) in &mut interaction_query {
-            *color = match (*interaction, selected) {
-                (Interaction::Clicked, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
-                (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
-                (Interaction::Hovered, None) => HOVERED_BUTTON.into(),
-                (Interaction::None, None) => NORMAL_BUTTON.into(),
-            }
-        }
+    // The save and the game in progress, for the buttons that continue, start or resume a game
+    #[derive(SystemParam)]
+    struct SavedGame<'w, 's> {
+        loader: SaveLoader<'w, 's>,
+        asset_server: Res<'w, AssetServer>,
+        away_summary: Option<Res<'w, AwaySummary>>,
+        in_progress: Option<Res<'w, GameInProgress>>,
+        pets: Query<'w, 's, Entity, With<Pet>>,
     }
 
-    // This system updates the settings when a new value for a setting is selected, and marks
-    // the button as the one currently selected
-    
+    // What was picked on the new game screen, only there while it is open
+    #[derive(SystemParam)]
+    struct NewGameForm<'w, 's> {
+        species: Option<Res<'w, ChosenSpecies>>,
+        name_input: Option<ResMut<'w, PetNameInput>>,
+        warning_text: Query<'w, 's, &'static mut Text, With<OverwriteWarningText>>,
+    }
 
-    
-    fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
-        menu_state.set(MenuState::Main);
+    // Coins of the player and what they were spent on
+    #[derive(SystemParam)]
+    struct Market<'w> {
+        wallet: ResMut<'w, Wallet>,
+        ledger: ResMut<'w, Ledger>,
     }
 
-    fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-        // Common style for all buttons on the screen
-        let button_style = Style {
-            size: Size::new(Val::Px(250.0), Val::Px(65.0)),
-            margin: UiRect::all(Val::Px(20.0)),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        };
-        let button_icon_style = Style {
-            size: Size::new(Val::Px(30.0), Val::Auto),
-            // This takes the icons out of the flexbox flow, to be positioned exactly
-            position_type: PositionType::Absolute,
-            // The icon will be close to the left border of the button
-            position: UiRect {
-                left: Val::Px(10.0),
-                right: Val::Auto,
-                top: Val::Auto,
-                bottom: Val::Auto,
-            },
-            ..default()
-        };
-        let button_text_style = TextStyle {
-            font: font.clone(),
-            font_size: 25.0,
-            color: TEXT_COLOR,
-        };
+    // Stats under these values are shown in warning and critical colours on the status screen
+    const STAT_WARNING: f32 = 50.0;
+    const STAT_CRITICAL: f32 = 25.0;
 
-        commands
-            .spawn((
-                NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
+    // Button of the controls screen that binds an action to the next key or button pressed
+    #[derive(Component)]
+    struct RebindButton(Action);
+
+    // Filled part of the bar showing a stat on the status screen
+    #[derive(Component)]
+    struct StatBar(Stat);
+
+    // Text showing the value of a stat on the status screen
+    #[derive(Component)]
+    struct StatValueText(Stat);
+
+    // Text showing the age of the pet on the status screen
+    #[derive(Component)]
+    struct PetAgeText;
+
+    // Tag component used to tag entities added on the "while you were away" screen
+    #[derive(Component)]
+    struct OnAwayScreen;
+
+    // Tag component used to tag entities added on the memorial screen
+    #[derive(Component)]
+    struct OnMemorialScreen;
+
+    // Actions of the transaction screen, `Buy` holds the index of the offer in `market::offers()`
+    #[derive(Component)]
+    enum TransactionButtonAction {
+        Buy(usize),
+        Sell,
+    }
+
+    // Text explaining why a transaction didn't go through
+    #[derive(Component)]
+    struct MarketMessageText;
+
+    // Tag component used to tag entities added on the vendor shop screen
+    #[derive(Component)]
+    struct OnShopScreen;
+
+    // Actions of the shop screen, `Buy` holds the index of the item in the catalogue
+    #[derive(Component)]
+    enum ShopButtonAction {
+        Buy(usize),
+    }
+
+    // Text showing the coins left on the shop screen
+    #[derive(Component)]
+    struct ShopCoinsText;
+
+    // Text telling what was just bought, or why it couldn't be
+    #[derive(Component)]
+    struct ShopMessageText;
+
+    // Buttons of the main menu, from `assets/ui/main.menu.ron`
+    #[derive(Resource, Deref)]
+    struct MainMenuFile(Handle<MenuFile>);
+
+    // All actions that can be triggered from a button click
+    #[derive(Component, Clone, PartialEq, Deserialize)]
+    enum MenuButtonAction {
+        NewGame, //Create a new Game
+        ContinueGame, //Continue the Game
+        ResumeGame, //Enter the game after the away summary
+        StartNewGame, //Start the game with the pet set up on the new game screen
+        Settings,//Game settings
+        Controls, //Action bindings, from the settings
+        Update, //Pet state update
+        Transaction,//Buy or Sell pet
+        Shop, //Buy items from the vendor
+        BackToMainMenu,    
+        BackToSettings,
+        Quit,
+    }
+
+
+    // This system updates the settings when a new value for a setting is selected, and marks
+    // the button as the one currently selected
+    fn setting_button<T: Resource + Component + PartialEq + Copy>(
+        interaction_query: Query<(&Interaction, &T, Entity), (Changed<Interaction>, With<Button>)>,
+        mut selected_query: Query<(Entity, &mut BackgroundColor), (With<SelectedOption>, With<T>)>,
+        mut commands: Commands,
+        mut setting: ResMut<T>,
+        theme: Res<UiTheme>,
+    ) {
+        for (interaction, button_setting, entity) in &interaction_query {
+            if *interaction == Interaction::Clicked && *setting != *button_setting {
+                // No button is selected if the setting has a value the screen doesn't offer
+                if let Ok((previous_button, mut previous_color)) = selected_query.get_single_mut() {
+                    *previous_color = theme.button.into();
+                    commands.entity(previous_button).remove::<SelectedOption>();
+                }
+                commands.entity(entity).insert(SelectedOption);
+                *setting = *button_setting;
+            }
+        }
+    }
+
+    
+    fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
+        menu_state.set(MenuState::Main);
+    }
+
+    pub const MAIN_MENU_PATH: &str = "ui/main.menu.ron";
+
+    fn load_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(MainMenuFile(asset_server.load(MAIN_MENU_PATH)));
+    }
+
+    // Show the main menu again when its file changes, and the current screen when the theme does
+    fn reload_main_menu(
+        mut events: EventReader<AssetEvent<MenuFile>>,
+        main_menu_file: Res<MainMenuFile>,
+        theme: Res<UiTheme>,
+        current_state: Res<State<MenuState>>,
+        mut menu_state: ResMut<NextState<MenuState>>,
+    ) {
+        if asset_changed(&mut events, &main_menu_file) && current_state.0 == MenuState::Main {
+            menu_state.set(MenuState::Main);
+        }
+        // Every menu screen is restyled when the theme changes
+        if theme.is_changed() && !theme.is_added() && current_state.0 != MenuState::Disabled {
+            menu_state.set(current_state.0);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn main_menu_setup(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        menu_files: Res<Assets<MenuFile>>,
+        main_menu_file: Res<MainMenuFile>,
+        theme: Res<UiTheme>,
+        notice: Option<Res<MenuNotice>>,
+        loader: SaveLoader,
+        pets: Query<(), With<Pet>>,
+    ) {
+        // Nothing to show until the file is loaded, the menu is shown again then
+        let Some(menu) = menu_files.get(&main_menu_file) else {
+            return;
+        };
+        // Continue is only available when there is a save to load or a pet to play with
+        let can_continue = loader.pending() || !pets.is_empty();
+        spawn_screen(&mut commands, OnMainMenuScreen).with_children(|parent| {
+            spawn_menu(parent, &asset_server, &theme, &menu.menu::<MenuButtonAction>(), |action| {
+                *action != MenuButtonAction::ContinueGame || can_continue
+            });
+            if let Some(notice) = notice {
+                parent.spawn(
+                    TextBundle::from_section(
+                        notice.0.clone(),
+                        TextStyle { color: theme.danger, ..theme.text_style(20.0) },
+                    )
+                    .with_style(Style { margin: UiRect::all(Val::Px(10.0)), ..default() }),
+                );
+            }
+        });
+    }
+    //New game menu setup
+    fn new_game_setup(mut commands: Commands, game_assets: Res<GameAssets>, theme: Res<UiTheme>) {
+        let button_style = button_style(200.0, 65.0, 10.0);
+        let button_icon_style = Style {
+            size: Size::new(Val::Px(40.0), Val::Auto),
+            margin: UiRect::right(Val::Px(10.0)),
+            ..default()
+        };
+        let button_text_style = theme.text_style(25.0);
+        let chosen_species = ChosenSpecies::default();
+        commands.insert_resource(chosen_species);
+        commands.insert_resource(PetNameInput::default());
+
+        spawn_screen(&mut commands, OnNewGameScreen).with_children(|parent| {
+            spawn_panel(parent, theme.panel, 0.0).with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(
+                        "Adopt a new pet",
+                        theme.text_style(30.0),
+                    )
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(20.0)),
                         ..default()
-                    },
-                    ..default()
-                },
-                OnMainMenuScreen,
-            ))
-            .with_children(|parent| {
+                    }),
+                );
+
+                // Display a button for each species, the chosen one is highlighted
                 parent
                     .spawn(NodeBundle {
                         style: Style {
-                            flex_direction: FlexDirection::Column,
-                            align_items: AlignItems::Center,
+                            flex_direction: FlexDirection::Row,
                             ..default()
                         },
-                        background_color: Color::DARK_GREEN.into(),
                         ..default()
                     })
                     .with_children(|parent| {
-                        // Display the game name
-                        parent.spawn(
-                            TextBundle::from_section(
-                                "Welcome to Window Pet!",
-                                TextStyle {
-                                    font: font.clone(),
-                                    font_size: 30.0,
-                                    color: TEXT_COLOR,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::all(Val::Px(20.0)),
-                                ..default()
-                            }),
-                        );
-
-                        // Display buttons for each action available from the main menu:
-                        // - new game
-                        // - settings
-                        // - quit
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                MenuButtonAction::NewGame,
-                            ))
-                            .with_children(|parent| {
-                                let icon = asset_server.load("textures/Game Icons/right.png");
+                        for species in Species::ALL {
+                            let mut entity = parent.spawn((button_bundle(&theme, button_style.clone()), ChosenSpecies(species)));
+                            entity.with_children(|parent| {
                                 parent.spawn(ImageBundle {
                                     style: button_icon_style.clone(),
-                                    image: UiImage::new(icon),
+                                    image: UiImage::new(game_assets.species_icon(species)),
                                     ..default()
                                 });
                                 parent.spawn(TextBundle::from_section(
-                                    "New Game",
+                                    species.name(),
                                     button_text_style.clone(),
                                 ));
                             });
+                            if chosen_species == ChosenSpecies(species) {
+                                entity.insert((SelectedOption, BackgroundColor(theme.pressed_button)));
+                            }
+                        }
+                    });
+
+                // Display the name typed so far
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section("Name:", button_text_style.clone()));
                         parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(250.0), Val::Px(40.0)),
+                                    margin: UiRect::left(Val::Px(10.0)),
+                                    padding: UiRect::horizontal(Val::Px(8.0)),
+                                    align_items: AlignItems::Center,
                                     ..default()
                                 },
-                                MenuButtonAction::ContinueGame,
-                            ))
+                                background_color: theme.button.into(),
+                                ..default()
+                            })
                             .with_children(|parent| {
-                                let icon = asset_server.load("textures/Game Icons/right.png");
-                                parent.spawn(ImageBundle {
-                                    style: button_icon_style.clone(),
-                                    image: UiImage::new(icon),
-                                    ..default()
-                                });
-                                parent.spawn(TextBundle::from_section(
-                                    "Continue",
-                                    button_text_style.clone(),
+                                parent.spawn((
+                                    TextBundle::from_section("_", button_text_style.clone()),
+                                    PetNameText,
                                 ));
                             });
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                MenuButtonAction::Settings,
-                            ))
-                            .with_children(|parent| {
-                                let icon = asset_server.load("textures/Game Icons/wrench.png");
-                                parent.spawn(ImageBundle {
-                                    style: button_icon_style.clone(),
-                                    image: UiImage::new(icon),
+                    });
+
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            color: theme.highlight,
+                            ..theme.text_style(20.0)
+                        },
+                    ),
+                    OverwriteWarningText,
+                ));
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_button(parent, &theme, button_style.clone(), "Start", button_text_style.clone(), MenuButtonAction::StartNewGame);
+                        spawn_button(parent, &theme, button_style, "Back", button_text_style, MenuButtonAction::BackToMainMenu);
+                    });
+            });
+        });
+    }
+
+    // Type the name of the new pet with the keyboard
+    fn pet_name_input(
+        mut characters: EventReader<ReceivedCharacter>,
+        keyboard_input: Res<Input<KeyCode>>,
+        mut name_input: ResMut<PetNameInput>,
+        mut name_text: Query<&mut Text, With<PetNameText>>,
+    ) {
+        let mut changed = false;
+        for event in characters.iter() {
+            if !event.char.is_control() && name_input.name.chars().count() < MAX_PET_NAME_LEN {
+                name_input.name.push(event.char);
+                changed = true;
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            changed |= name_input.name.pop().is_some();
+        }
+        if changed {
+            for mut text in &mut name_text {
+                text.sections[0].value = format!("{}_", name_input.name);
+            }
+        }
+    }
+
+    // Summary of how the pet did while the game was closed
+    fn away_setup(mut commands: Commands, theme: Res<UiTheme>, summary: Res<AwaySummary>) {
+        let text_style = theme.text_style(25.0);
+        let mut lines = vec![format!("You were away for {}", format_duration(summary.away))];
+        if summary.simulated < summary.away {
+            lines.push(format!("(your pet only remembers the last {})", format_duration(summary.simulated)));
+        }
+        for stat in Stat::ALL {
+            lines.push(format!(
+                "{}: {:.0} -> {:.0}",
+                stat.name(),
+                stat.value(&summary.before),
+                stat.value(&summary.after)
+            ));
+        }
+
+        spawn_screen(&mut commands, OnAwayScreen).with_children(|parent| {
+            spawn_panel(parent, theme.panel, 20.0).with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(
+                        "While you were away...",
+                        theme.text_style(30.0),
+                    )
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    }),
+                );
+                for line in lines {
+                    parent.spawn(TextBundle::from_section(line, text_style.clone()));
+                }
+                spawn_button(parent, &theme, button_style(250.0, 65.0, 20.0), "Continue", text_style, MenuButtonAction::ResumeGame);
+            });
+        });
+    }
+
+    // A pet just died or ran away, leave whatever menu screen is shown for its memorial
+    fn open_memorial(mut commands: Commands, mut menu_state: ResMut<NextState<MenuState>>) {
+        commands.remove_resource::<MemorialPending>();
+        commands.remove_resource::<AwaySummary>();
+        menu_state.set(MenuState::Memorial);
+    }
+
+    // Memorial of the last pet that left, with a way to start over
+    fn memorial_setup(mut commands: Commands, theme: Res<UiTheme>, graveyard: Res<Graveyard>) {
+        let text_style = theme.text_style(25.0);
+        let button_style = button_style(250.0, 65.0, 10.0);
+        let (title, lines) = match graveyard.last() {
+            Some(memorial) => (
+                format!(
+                    "{} the {}",
+                    memorial.name,
+                    memorial.evolution.as_deref().unwrap_or(memorial.species.name())
+                ),
+                vec![
+                    match memorial.fate {
+                        Fate::Died => "died of neglect...".to_string(),
+                        Fate::RanAway => "ran away, it was too unhappy...".to_string(),
+                    },
+                    format!("It lived for {}", format_duration(memorial.age as u64)),
+                ],
+            ),
+            None => ("Rest in peace".to_string(), Vec::new()),
+        };
+
+        spawn_screen(&mut commands, OnMemorialScreen).with_children(|parent| {
+            spawn_panel(parent, theme.memorial, 20.0).with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(
+                        title,
+                        theme.text_style(30.0),
+                    )
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    }),
+                );
+                for line in lines {
+                    parent.spawn(TextBundle::from_section(line, text_style.clone()));
+                }
+                spawn_button(parent, &theme, button_style.clone(), "New Game", text_style.clone(), MenuButtonAction::NewGame);
+                spawn_button(parent, &theme, button_style, "Back", text_style, MenuButtonAction::BackToMainMenu);
+            });
+        });
+    }
+
+    // Status of the pet with a bar per stat, kept up to date by `update_stat_bars`
+    fn update_setup(
+        mut commands: Commands,
+        theme: Res<UiTheme>,
+        pets: Query<(&PetProfile, &PetAge, &Growth, &Sickness), With<Pet>>,
+    ) {
+        let text_style = theme.text_style(25.0);
+
+        spawn_screen(&mut commands, OnUpdateScreen).with_children(|parent| {
+            spawn_panel(parent, theme.panel, 20.0).with_children(|parent| {
+                let Ok((profile, age, growth, sickness)) = pets.get_single() else {
+                    // The pet only exists once a game was started or continued
+                    parent.spawn(TextBundle::from_section(
+                        "You don't have a pet yet!",
+                        text_style.clone(),
+                    ));
+                    spawn_back_button(parent, &theme, text_style.clone());
+                    return;
+                };
+                parent.spawn(
+                    TextBundle::from_section(
+                        format!(
+                            "{} the {}",
+                            profile.name,
+                            growth.evolution.as_deref().unwrap_or(profile.species.name())
+                        ),
+                        theme.text_style(30.0),
+                    )
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    }),
+                );
+                parent.spawn((
+                    TextBundle::from_section(age_text(age, growth), text_style.clone()),
+                    PetAgeText,
+                ));
+                if let Some(ailment) = sickness.ailment {
+                    parent.spawn(TextBundle::from_section(
+                        format!("Sick: {}", ailment.name()),
+                        TextStyle {
+                            color: theme.danger,
+                            ..text_style.clone()
+                        },
+                    ));
+                }
+
+                for stat in Stat::ALL {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                margin: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(
+                                TextBundle::from_section(stat.name(), text_style.clone())
+                                    .with_style(Style {
+                                        size: Size::new(Val::Px(130.0), Val::Auto),
+                                        ..default()
+                                    }),
+                            );
+                            // Background of the bar, the filled part grows inside it
+                            parent
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(250.0), Val::Px(20.0)),
+                                        ..default()
+                                    },
+                                    background_color: theme.button.into(),
                                     ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        NodeBundle {
+                                            style: Style {
+                                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        StatBar(stat),
+                                    ));
                                 });
-                                parent.spawn(TextBundle::from_section(
-                                    "Settings", 
-                                    button_text_style.clone()
-                                ));
-                            });
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
+                            parent.spawn((
+                                TextBundle::from_section("", text_style.clone()).with_style(Style {
+                                    size: Size::new(Val::Px(60.0), Val::Auto),
+                                    margin: UiRect::left(Val::Px(10.0)),
                                     ..default()
-                                },
-                                //MenuButtonAction::Update,
-                            ))
-                            .with_children(|parent| {
-                                let icon = asset_server.load("textures/Game Icons/wrench.png");
-                                parent.spawn(ImageBundle {
-                                    style: button_icon_style.clone(),
-                                    image: UiImage::new(icon),
+                                }),
+                                StatValueText(stat),
+                            ));
+                        });
+                }
+                spawn_back_button(parent, &theme, text_style.clone());
+            });
+        });
+    }
+
+    fn spawn_back_button(parent: &mut ChildBuilder, theme: &UiTheme, text_style: TextStyle) {
+        spawn_button(parent, theme, button_style(250.0, 65.0, 20.0), "Back", text_style, MenuButtonAction::BackToMainMenu);
+    }
+
+    // Keep the bars of the status screen in sync with the pet stats
+    fn update_stat_bars(
+        pets: Query<(&PetStats, &PetAge, &Growth), With<Pet>>,
+        mut bars: Query<(&StatBar, &mut Style, &mut BackgroundColor)>,
+        mut value_texts: Query<(&StatValueText, &mut Text), Without<PetAgeText>>,
+        mut age_texts: Query<&mut Text, With<PetAgeText>>,
+        theme: Res<UiTheme>,
+    ) {
+        let Ok((stats, age, growth)) = pets.get_single() else {
+            return;
+        };
+        for (bar, mut style, mut color) in &mut bars {
+            let value = bar.0.value(stats);
+            style.size.width = Val::Percent(value / STAT_MAX * 100.0);
+            *color = if value < STAT_CRITICAL {
+                theme.danger.into()
+            } else if value < STAT_WARNING {
+                theme.highlight.into()
+            } else {
+                theme.good.into()
+            };
+        }
+        for (value_text, mut text) in &mut value_texts {
+            text.sections[0].value = format!("{:.0}", value_text.0.value(stats));
+        }
+        for mut text in &mut age_texts {
+            text.sections[0].value = age_text(age, growth);
+        }
+    }
+
+    fn age_text(age: &PetAge, growth: &Growth) -> String {
+        format!("Age: {} ({})", format_duration(age.0 as u64), growth.stage.name())
+    }
+
+    // Format a number of seconds as "2d 3h", "3h 12m" or "12m"
+    fn format_duration(seconds: u64) -> String {
+        let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+        if days > 0 {
+            format!("{days}d {hours}h")
+        } else if hours > 0 {
+            format!("{hours}h {minutes:02}m")
+        } else {
+            format!("{minutes}m")
+        }
+    }
+
+    // Market where pets are bought with coins, and where the current pet can be sold
+    fn transaction_setup(
+        mut commands: Commands,
+        theme: Res<UiTheme>,
+        wallet: Res<Wallet>,
+        pets: Query<(&PetProfile, &PetStats, &PetAge), With<Pet>>,
+    ) {
+        let button_style = button_style(120.0, 40.0, 4.0);
+        let text_style = theme.text_style(25.0);
+
+        spawn_screen(&mut commands, OnTransactionScreen).with_children(|parent| {
+            spawn_panel(parent, theme.panel, 10.0).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    format!("Market - {} coins", wallet.0),
+                    theme.text_style(30.0),
+                ));
+
+                // The current pet can be sold, the price goes up with good care and age
+                if let Ok((profile, stats, age)) = pets.get_single() {
+                    spawn_button(
+                        parent,
+                        &theme,
+                        Style {
+                            size: Size::new(Val::Px(400.0), Val::Px(45.0)),
+                            ..button_style.clone()
+                        },
+                        format!("Sell {} for {}", profile.name, market::sell_price(profile, stats, age)),
+                        text_style.clone(),
+                        TransactionButtonAction::Sell,
+                    );
+                }
+
+                // One row per pet for sale
+                for (index, offer) in market::offers().iter().enumerate() {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(
+                                TextBundle::from_section(
+                                    format!(
+                                        "{} {} - {}",
+                                        offer.rarity.name(),
+                                        offer.species.name(),
+                                        offer.price()
+                                    ),
+                                    text_style.clone(),
+                                )
+                                .with_style(Style {
+                                    size: Size::new(Val::Px(280.0), Val::Auto),
                                     ..default()
-                                });
-                                parent.spawn(TextBundle::from_section(
-                                    "Update", 
-                                    button_text_style.clone()
-                                ));
-                            });       
+                                }),
+                            );
+                            spawn_button(parent, &theme, button_style.clone(), "Buy", text_style.clone(), TransactionButtonAction::Buy(index));
+                        });
+                }
+
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            color: theme.highlight,
+                            ..theme.text_style(20.0)
+                        },
+                    ),
+                    MarketMessageText,
+                ));
+                spawn_back_button(parent, &theme, text_style.clone());
+            });
+        });
+    }
+
+    // Buy or sell a pet, then rebuild the screen to show the new balance
+    fn transaction_action(
+        interaction_query: Query<(&Interaction, &TransactionButtonAction), (Changed<Interaction>, With<Button>)>,
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        mut menu_state: ResMut<NextState<MenuState>>,
+        mut market: Market,
+        pets: Query<(Entity, &PetProfile, &PetStats, &PetAge), With<Pet>>,
+        mut message_text: Query<&mut Text, With<MarketMessageText>>,
+    ) {
+        for (interaction, action) in &interaction_query {
+            if *interaction != Interaction::Clicked {
+                continue;
+            }
+            let refusal = match action {
+                TransactionButtonAction::Sell => match pets.get_single() {
+                    Ok((entity, profile, stats, age)) => {
+                        let price = market::sell_price(profile, stats, age);
+                        market.wallet.0 += price;
+                        market.ledger.record(TransactionKind::Sold, profile, price);
+                        commands.entity(entity).despawn_recursive();
+                        None
+                    }
+                    Err(_) => Some("You don't have a pet to sell"),
+                },
+                TransactionButtonAction::Buy(index) => {
+                    let offer = market::offers()[*index];
+                    if !pets.is_empty() {
+                        Some("You can only keep one pet, sell yours first")
+                    } else if market.wallet.0 < offer.price() {
+                        Some("You don't have enough coins")
+                    } else {
+                        let profile = PetProfile {
+                            name: offer.species.name().to_string(),
+                            species: offer.species,
+                            rarity: offer.rarity,
+                        };
+                        market.wallet.0 -= offer.price();
+                        market.ledger.record(TransactionKind::Bought, &profile, offer.price());
+                        spawn_pet(&mut commands, &asset_server, &PetSave { profile, ..default() });
+                        // Buying a pet is enough to get a game going
+                        commands.insert_resource(GameInProgress);
+                        None
+                    }
+                }
+            };
+            match refusal {
+                Some(message) => {
+                    for mut text in &mut message_text {
+                        text.sections[0].value = message.to_string();
+                    }
+                }
+                None => menu_state.set(MenuState::Transaction),
+            }
+        }
+    }
+
+    // Vendor selling the items of the catalogue, next to the vendor art
+    fn shop_setup(
+        mut commands: Commands,
+        game_assets: Res<GameAssets>,
+        theme: Res<UiTheme>,
+        wallet: Res<Wallet>,
+        catalogue_handle: Res<CatalogueHandle>,
+        catalogues: Res<Assets<ItemCatalogue>>,
+    ) {
+        let button_style = button_style(90.0, 36.0, 3.0);
+        let text_style = theme.text_style(22.0);
+
+        spawn_screen(&mut commands, OnShopScreen).with_children(|parent| {
+            // The vendor stands next to the panel
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(220.0), Val::Auto),
+                            margin: UiRect::right(Val::Px(10.0)),
+                            ..default()
+                        },
+                        image: UiImage::new(game_assets.vendor.clone()),
+                        ..default()
+                    });
+                    spawn_panel(parent, theme.panel, 10.0).with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                format!("Shop - {} coins", wallet.0),
+                                theme.text_style(30.0),
+                            ),
+                            ShopCoinsText,
+                        ));
+
+                        // One row per item of the catalogue
+                        let Some(catalogue) = catalogues.get(&catalogue_handle) else {
+                            parent.spawn(TextBundle::from_section(
+                                "The vendor is still unpacking, come back in a moment",
+                                text_style.clone(),
+                            ));
+                            spawn_back_button(parent, &theme, text_style.clone());
+                            return;
+                        };
+                        for (index, item) in catalogue.0.iter().enumerate() {
                             parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style,
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                               // MenuButtonAction::Transaction,
-                            ))
-                            .with_children(|parent| {
-                                let icon = asset_server.load("textures/Game Icons/wrench.png");
-                                parent.spawn(ImageBundle {
-                                    style: button_icon_style,
-                                    image: UiImage::new(icon),
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
                                     ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(
+                                        TextBundle::from_section(
+                                            format!("{} ({}) - {}", item.name, item.kind.name(), item.price),
+                                            text_style.clone(),
+                                        )
+                                        .with_style(Style {
+                                            size: Size::new(Val::Px(200.0), Val::Auto),
+                                            ..default()
+                                        }),
+                                    );
+                                    spawn_button(parent, &theme, button_style.clone(), "Buy", text_style.clone(), ShopButtonAction::Buy(index));
+                                    parent.spawn(TextBundle::from_section(
+                                        item.effect.summary(),
+                                        theme.text_style(18.0),
+                                    ));
                                 });
-                                parent.spawn(TextBundle::from_section(
-                                    "Transcation", 
-                                    button_text_style
-                                ));
-                            }); 
-                    
-                    
-                    
+                        }
+
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    color: theme.highlight,
+                                    ..theme.text_style(20.0)
+                                },
+                            ),
+                            ShopMessageText,
+                        ));
+                        spawn_back_button(parent, &theme, text_style.clone());
                     });
-                       
+                });
+        });
+    }
+
+    // Buy an item: the price is taken from the wallet and the item goes into the inventory
+    fn shop_action(
+        interaction_query: Query<(&Interaction, &ShopButtonAction), (Changed<Interaction>, With<Button>)>,
+        catalogue_handle: Res<CatalogueHandle>,
+        catalogues: Res<Assets<ItemCatalogue>>,
+        mut wallet: ResMut<Wallet>,
+        mut inventory: ResMut<Inventory>,
+        mut coins_text: Query<&mut Text, (With<ShopCoinsText>, Without<ShopMessageText>)>,
+        mut message_text: Query<&mut Text, With<ShopMessageText>>,
+    ) {
+        let Some(catalogue) = catalogues.get(&catalogue_handle) else {
+            return;
+        };
+        for (interaction, action) in &interaction_query {
+            if *interaction != Interaction::Clicked {
+                continue;
+            }
+            let ShopButtonAction::Buy(index) = action;
+            let Some(item) = catalogue.0.get(*index) else {
+                continue;
+            };
+            let message = if wallet.0 < item.price {
+                "You don't have enough coins".to_string()
+            } else if inventory.add(item, 1) > 0 {
+                "Your inventory is full".to_string()
+            } else {
+                wallet.0 -= item.price;
+                format!("Bought {}, you now have {}", item.name, inventory.count(&item.id))
+            };
+            for mut text in &mut message_text {
+                text.sections[0].value = message.clone();
+            }
+            for mut text in &mut coins_text {
+                text.sections[0].value = format!("Shop - {} coins", wallet.0);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn settings_menu_setup(
+        mut commands: Commands,
+        theme: Res<UiTheme>,
+        display_quality: Res<DisplayQuality>,
+        volume: Res<Volume>,
+        window_scale: Res<WindowScale>,
+        always_on_top: Res<AlwaysOnTop>,
+        widget_mode: Res<WidgetMode>,
+        theme_preset: Res<ThemePreset>,
+        show_splash: Res<ShowSplash>,
+    ) {
+        let button_style = button_style(120.0, 45.0, 5.0);
+        let small_button_style = Style {
+            size: Size::new(Val::Px(35.0), Val::Px(45.0)),
+            ..button_style.clone()
+        };
+        let button_text_style = theme.text_style(25.0);
+
+        spawn_screen(&mut commands, OnSettingsMenuScreen).with_children(|parent| {
+            spawn_panel(parent, theme.panel, 10.0).with_children(|parent| {
+                // Display one row per setting, with a button for each possible value
+                spawn_setting_row(
+                    parent,
+                    "Display",
+                    DisplayQuality::ALL.map(|quality| (quality, format!("{quality:?}"))),
+                    *display_quality,
+                    &button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_setting_row(
+                    parent,
+                    "Volume",
+                    (0..=Volume::MAX).map(|level| (Volume(level), level.to_string())),
+                    *volume,
+                    &small_button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_setting_row(
+                    parent,
+                    "Size",
+                    WindowScale::ALL.map(|scale| (scale, format!("{}x", scale.factor()))),
+                    *window_scale,
+                    &button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_setting_row(
+                    parent,
+                    "On top",
+                    [(AlwaysOnTop(false), "Off".to_string()), (AlwaysOnTop(true), "On".to_string())],
+                    *always_on_top,
+                    &button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_setting_row(
+                    parent,
+                    "Widget",
+                    [(WidgetMode(false), "Off".to_string()), (WidgetMode(true), "On".to_string())],
+                    *widget_mode,
+                    &button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_setting_row(
+                    parent,
+                    "Theme",
+                    ThemePreset::ALL.map(|preset| (preset, preset.name().to_string())),
+                    *theme_preset,
+                    &button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_setting_row(
+                    parent,
+                    "Splash",
+                    [(ShowSplash(false), "Off".to_string()), (ShowSplash(true), "On".to_string())],
+                    *show_splash,
+                    &button_style,
+                    &theme,
+                    &button_text_style,
+                );
+                spawn_button(
+                    parent,
+                    &theme,
+                    Style {
+                        size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+                        margin: UiRect::new(Val::Px(20.0), Val::Px(20.0), Val::Px(20.0), Val::Px(0.0)),
+                        ..button_style.clone()
+                    },
+                    "Controls",
+                    button_text_style.clone(),
+                    MenuButtonAction::Controls,
+                );
+                spawn_back_button(parent, &theme, button_text_style.clone());
             });
+        });
     }
-    //New game menu setup
-    fn new_game_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-        commands
-        .spawn((
-            TextBundle::from_section(
-                "This menu will be update later!",
-                TextStyle {
-                    font: font,
-                    font_size: 50.0,
-                    color: TEXT_COLOR,
+
+    // Spawn a label followed by one button per value of a setting, the current value is selected
+    fn spawn_setting_row<T: Component + PartialEq + Copy>(
+        parent: &mut ChildBuilder,
+        label: &str,
+        values: impl IntoIterator<Item = (T, String)>,
+        current: T,
+        button_style: &Style,
+        theme: &UiTheme,
+        button_text_style: &TextStyle,
+    ) {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    ..default()
                 },
-            )
-            .with_style(Style {
-                margin:UiRect::all(Val::Px(20.0)),
                 ..default()
-            }),
-            OnNewGameScreen,
-        ));
-        //////////////////////////////////////////////////////////////////////
+            })
+            .with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(label, button_text_style.clone()).with_style(Style {
+                        size: Size::new(Val::Px(100.0), Val::Auto),
+                        ..default()
+                    }),
+                );
+                for (value, text) in values {
+                    let mut entity = spawn_button(parent, theme, button_style.clone(), text, button_text_style.clone(), value);
+                    if value == current {
+                        entity.insert((SelectedOption, BackgroundColor(theme.pressed_button)));
+                    }
+                }
+            });
+    }
+
+    fn controls_menu_setup(mut commands: Commands, theme: Res<UiTheme>, bindings: Res<InputBindings>) {
+        let button_style = button_style(120.0, 45.0, 5.0);
+        let button_text_style = theme.text_style(25.0);
 
+        spawn_screen(&mut commands, OnControlsScreen).with_children(|parent| {
+            spawn_panel(parent, theme.panel, 10.0).with_children(|parent| {
+                // One row per action, showing what it is bound to
+                for action in Action::ALL {
+                    let bound: Vec<String> = bindings.get(action).iter().map(|binding| binding.name()).collect();
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(
+                                TextBundle::from_section(action.name(), button_text_style.clone()).with_style(Style {
+                                    size: Size::new(Val::Px(120.0), Val::Auto),
+                                    ..default()
+                                }),
+                            );
+                            spawn_button(
+                                parent,
+                                &theme,
+                                Style {
+                                    size: Size::new(Val::Px(380.0), Val::Px(40.0)),
+                                    ..button_style.clone()
+                                },
+                                bound.join(", "),
+                                theme.text_style(20.0),
+                                RebindButton(action),
+                            );
+                        });
+                }
+                spawn_button(
+                    parent,
+                    &theme,
+                    Style {
+                        size: Size::new(Val::Px(250.0), Val::Px(55.0)),
+                        margin: UiRect::all(Val::Px(20.0)),
+                        ..button_style
+                    },
+                    "Back",
+                    button_text_style,
+                    MenuButtonAction::BackToSettings,
+                );
+            });
+        });
     }
 
-    fn settings_menu_setup() {
-       
+    // Clicking the binding of an action waits for a key or button, which then replaces it
+    #[allow(clippy::too_many_arguments)]
+    fn rebind_action(
+        mut commands: Commands,
+        interaction_query: Query<(&Interaction, &RebindButton, &Children), (Changed<Interaction>, With<Button>)>,
+        buttons: Query<&Interaction, With<Button>>,
+        mut texts: Query<&mut Text>,
+        actions: Actions,
+        rebinding: Option<Res<Rebinding>>,
+        mut bindings: ResMut<InputBindings>,
+        mut menu_state: ResMut<NextState<MenuState>>,
+    ) {
+        // The click or key that started listening is over by the next frame, so it isn't bound
+        if let (Some(rebinding), Some(binding)) = (rebinding, actions.any_just_pressed()) {
+            commands.remove_resource::<Rebinding>();
+            // Clicking a button of the screen gives up instead, and lets the button do its job
+            let on_button = buttons.iter().any(|interaction| *interaction != Interaction::None);
+            if !(matches!(binding, Binding::Mouse(_)) && on_button) {
+                bindings.rebind(rebinding.0, binding);
+                // Show the new bindings
+                menu_state.set(MenuState::Controls);
+                return;
+            }
+        }
+        for (interaction, button, children) in &interaction_query {
+            if *interaction == Interaction::Clicked {
+                commands.insert_resource(Rebinding(button.0));
+                for child in children {
+                    if let Ok(mut text) = texts.get_mut(*child) {
+                        text.sections[0].value = "Press a key or a button...".to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    // Actions work again when leaving the screen while waiting for a key
+    fn stop_rebinding(mut commands: Commands) {
+        commands.remove_resource::<Rebinding>();
+    }
+
+    // Cancelling goes back to the screen this one was opened from
+    fn menu_back(
+        actions: Actions,
+        current_state: Res<State<MenuState>>,
+        mut menu_state: ResMut<NextState<MenuState>>,
+    ) {
+        if !actions.just_pressed(Action::Cancel) {
+            return;
+        }
+        match current_state.0 {
+            MenuState::Main | MenuState::Disabled => {}
+            MenuState::Controls => menu_state.set(MenuState::Settings),
+            _ => menu_state.set(MenuState::Main),
+        }
     }
 
     fn menu_action(interaction_query: Query<(&Interaction, &MenuButtonAction),(Changed<Interaction>, With<Button>),>,
         mut app_exit_events: EventWriter<AppExit>,mut menu_state: ResMut<NextState<MenuState>>,
         mut game_state: ResMut<NextState<GameState>>,
+        mut commands: Commands,
+        mut saved: SavedGame,
+        mut form: NewGameForm,
     ) {
       
         for (interaction, menu_button_action) in &interaction_query {
@@ -789,17 +2046,89 @@ mod menu {
                 match menu_button_action {
                     MenuButtonAction::Quit => app_exit_events.send(AppExit),
                     MenuButtonAction::NewGame => menu_state.set(MenuState::NewGame),
-                    MenuButtonAction::ContinueGame => {
+                    // Load the save if it wasn't yet, and show what happened while the game was closed
+                    MenuButtonAction::ContinueGame => match saved.loader.load() {
+                        // The pet is gone, its memorial opens instead
+                        Ok(Some(Resumed::PetLeft)) => commands.remove_resource::<MenuNotice>(),
+                        Ok(Some(Resumed::NoPet)) => {
+                            commands.insert_resource(MenuNotice(
+                                "You have no pet, get one at the market".to_string(),
+                            ));
+                            menu_state.set(MenuState::Main);
+                        }
+                        Ok(Some(Resumed::Away)) => {
+                            commands.remove_resource::<MenuNotice>();
+                            menu_state.set(MenuState::Away);
+                        }
+                        Ok(None) if saved.away_summary.is_some() => menu_state.set(MenuState::Away),
+                        Ok(Some(Resumed::Pet) | None) => {
+                            commands.remove_resource::<MenuNotice>();
+                            game_state.set(GameState::Game);
+                            menu_state.set(MenuState::Disabled);
+                        }
+                        Err(err) => {
+                            error!("Failed to load the save: {err}");
+                            commands.insert_resource(MenuNotice(format!("Could not load the save: {err}")));
+                            menu_state.set(MenuState::Main);
+                        }
+                    },
+                    MenuButtonAction::StartNewGame => {
+                        let (Some(species), Some(name_input)) = (form.species.as_ref(), form.name_input.as_mut()) else {
+                            continue;
+                        };
+                        // Ask for a second click before replacing the current game
+                        if (saved.in_progress.is_some() || save::save_exists()) && !name_input.overwrite_confirmed {
+                            name_input.overwrite_confirmed = true;
+                            for mut text in &mut form.warning_text {
+                                text.sections[0].value =
+                                    "Your current save will be archived! Press Start again to confirm".to_string();
+                            }
+                            continue;
+                        }
+                        // Keep the old save (and its memorials) around instead of overwriting it
+                        if save::save_exists() {
+                            if let Err(err) = save::archive() {
+                                error!("Failed to archive the save: {err}");
+                            }
+                        }
+                        let name = name_input.name.trim();
+                        let profile = PetProfile {
+                            name: if name.is_empty() { species.0.name().to_string() } else { name.to_string() },
+                            species: species.0,
+                            ..default()
+                        };
+                        save::restore(&mut commands, &saved.asset_server, &saved.pets, save::SaveFile::new_game(profile));
+                        commands.remove_resource::<AwaySummary>();
+                        commands.remove_resource::<MenuNotice>();
+                        game_state.set(GameState::Game);
+                        menu_state.set(MenuState::Disabled);
+                    }
+                    MenuButtonAction::ResumeGame => {
+                        commands.remove_resource::<AwaySummary>();
                         game_state.set(GameState::Game);
                         menu_state.set(MenuState::Disabled);
                     }
                     
                     MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
-                    MenuButtonAction::Update => menu_state.set(MenuState::Update),
-                    MenuButtonAction::Transaction => menu_state.set(MenuState::Transcation),
+                    MenuButtonAction::Controls => menu_state.set(MenuState::Controls),
+                    // These screens show the saved game, load it first so they don't show a blank one
+                    MenuButtonAction::Update | MenuButtonAction::Transaction | MenuButtonAction::Shop => {
+                        if let Err(err) = saved.loader.load() {
+                            error!("Failed to load the save: {err}");
+                            commands.insert_resource(MenuNotice(format!("Could not load the save: {err}")));
+                            menu_state.set(MenuState::Main);
+                            continue;
+                        }
+                        menu_state.set(match menu_button_action {
+                            MenuButtonAction::Update => MenuState::Update,
+                            MenuButtonAction::Transaction => MenuState::Transaction,
+                            _ => MenuState::Shop,
+                        });
+                    }
                     
 
                     MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
+                    MenuButtonAction::BackToSettings => menu_state.set(MenuState::Settings),
                     
                     _=>menu_state.set(MenuState::Main),
                 }
@@ -808,6 +2137,51 @@ mod menu {
      
     }
 
+    #[cfg(test)]
+    mod tests {
+        use std::time::{Duration, Instant};
+
+        use bevy::core::TaskPoolPlugin;
+
+        use super::*;
+        use crate::pet::{PetActivity, PetPlugin};
+
+        // The pet keeps living while the status screen is open, and its bars follow
+        #[test]
+        fn status_bars_move_over_time() {
+            let mut app = App::new();
+            // The theme loads its font
+            app.add_plugin(TaskPoolPlugin::default())
+                .add_plugin(AssetPlugin::default())
+                .add_state::<GameState>()
+                .add_state::<MenuState>()
+                .add_plugin(PetPlugin)
+                .init_resource::<UiTheme>()
+                .insert_resource(GameInProgress)
+                .add_system(update_stat_bars.in_set(OnUpdate(MenuState::Update)));
+            app.world.spawn((Pet, PetActivity::default(), PetStats::default(), PetAge::default(), Growth::default()));
+            let bar = app.world.spawn((StatBar(Stat::Hunger), Style::default(), BackgroundColor::default())).id();
+            app.world.resource_mut::<NextState<GameState>>().set(GameState::Menu);
+            app.world.resource_mut::<NextState<MenuState>>().set(MenuState::Update);
+
+            let start = Instant::now();
+            let mut time = Time::default();
+            time.update_with_instant(start);
+            app.insert_resource(time);
+            app.update();
+            let width = |app: &App| app.world.get::<Style>(bar).unwrap().size.width;
+            assert_eq!(width(&app), Val::Percent(100.0));
+
+            app.world.resource_mut::<Time>().update_with_instant(start + Duration::from_secs(100));
+            // The bars may be updated before the stats decay, they follow on the next frame
+            app.update();
+            app.update();
+            let Val::Percent(width) = width(&app) else {
+                panic!("the bar width is a percentage");
+            };
+            assert!(width < 100.0, "the bar didn't move: {width}");
+        }
+    }
 }
 
 // Generic system that takes a component as a parameter, and will despawn all entities with that component
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::pet::{PetAge, PetProfile, PetStats, Rarity, Species};
+use super::save::now;
+
+// This plugin holds the money of the player and the record of the pets bought and sold
+pub struct MarketPlugin;
+
+impl Plugin for MarketPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Wallet>().init_resource::<Ledger>();
+    }
+}
+
+// Money the player starts a new game with
+pub const STARTING_MONEY: u32 = 200;
+
+// Coins owned by the player
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Deref, DerefMut)]
+pub struct Wallet(pub u32);
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Wallet(STARTING_MONEY)
+    }
+}
+
+// Every pet bought or sold, oldest first
+#[derive(Resource, Clone, Debug, Default, Deref, DerefMut)]
+pub struct Ledger(pub Vec<Transaction>);
+
+impl Ledger {
+    pub fn record(&mut self, kind: TransactionKind, profile: &PetProfile, price: u32) {
+        self.0.push(Transaction {
+            at: now(),
+            kind,
+            name: profile.name.clone(),
+            species: profile.species,
+            rarity: profile.rarity,
+            price,
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionKind {
+    Bought,
+    Sold,
+}
+
+// One line of the ledger
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Transaction {
+    // Unix timestamp (in seconds) of the transaction
+    pub at: u64,
+    pub kind: TransactionKind,
+    pub name: String,
+    pub species: Species,
+    pub rarity: Rarity,
+    pub price: u32,
+}
+
+// A pet for sale on the market
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Offer {
+    pub species: Species,
+    pub rarity: Rarity,
+}
+
+impl Offer {
+    pub fn price(&self) -> u32 {
+        base_price(self.species, self.rarity)
+    }
+}
+
+// Pets that can be bought on the market
+pub fn offers() -> Vec<Offer> {
+    Rarity::ALL
+        .iter()
+        .flat_map(|rarity| {
+            Species::ALL.iter().map(|species| Offer {
+                species: *species,
+                rarity: *rarity,
+            })
+        })
+        .collect()
+}
+
+fn base_price(species: Species, rarity: Rarity) -> u32 {
+    let species_price = match species {
+        Species::Turtle => 50,
+        Species::Mani => 60,
+    };
+    species_price * rarity.price_factor()
+}
+
+// What the market pays for a pet: its base price, lowered when it is in bad shape and raised
+// (up to twice the base price) as it grows older
+pub fn sell_price(profile: &PetProfile, stats: &PetStats, age: &PetAge) -> u32 {
+    let condition = 0.5 + 0.5 * stats.care();
+    let hours = age.0 / 3600.0;
+    let maturity = 1.0 + (hours / 48.0).min(1.0);
+    (base_price(profile.species, profile.rarity) as f32 * condition * maturity).round() as u32
+}
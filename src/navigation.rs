@@ -0,0 +1,113 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+use super::actions::{Action, Actions};
+use super::ui::DisabledButton;
+
+// This plugin lets the menus be used without a mouse: the navigation actions (arrow keys and
+// D-pad by default) move the focus between the buttons of the screen, and the confirm action
+// (Enter or A) clicks the focused one
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        // Clicks have to be in place before the button systems look for them in `Update`
+        app.add_systems(
+            (move_focus, click_focused)
+                .chain()
+                .in_base_set(CoreSet::PreUpdate)
+                .after(UiSystem::Focus),
+        );
+    }
+}
+
+// The button the navigation actions move from, shown like a hovered button
+#[derive(Component)]
+pub struct Focused;
+
+// Buttons that can get the focus, with their centre on the screen
+type Focusable<'a> = (Entity, &'a GlobalTransform, &'a ComputedVisibility);
+
+// Move the focus to the closest button in the direction asked for. The first press only focuses
+// the top left button
+fn move_focus(
+    mut commands: Commands,
+    actions: Actions,
+    buttons: Query<Focusable, (With<Button>, Without<DisabledButton>)>,
+    focused: Query<Entity, (With<Focused>, With<Button>)>,
+    mut interactions: Query<&mut Interaction>,
+) {
+    let direction = [
+        (Action::NavigateUp, Vec2::NEG_Y),
+        (Action::NavigateDown, Vec2::Y),
+        (Action::NavigateLeft, Vec2::NEG_X),
+        (Action::NavigateRight, Vec2::X),
+    ]
+    .into_iter()
+    .find_map(|(action, direction)| actions.just_pressed(action).then_some(direction));
+    let Some(direction) = direction else {
+        return;
+    };
+    // UI coordinates go down from the top left corner of the window
+    let visible = buttons
+        .iter()
+        .filter(|(_, _, visibility)| visibility.is_visible())
+        .map(|(entity, transform, _)| (entity, transform.translation().truncate()));
+    let current = focused
+        .get_single()
+        .ok()
+        .and_then(|entity| Some((entity, buttons.get(entity).ok()?.1.translation().truncate())));
+    let next = match current {
+        Some((current, from)) => visible
+            .filter(|(entity, _)| *entity != current)
+            .filter_map(|(entity, position)| {
+                let offset = position - from;
+                let along = offset.dot(direction);
+                // Buttons straight ahead are preferred to closer ones off to the side
+                let aside = (offset - direction * along).length();
+                (along > 1.0).then_some((entity, along + 2.0 * aside))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity),
+        None => visible
+            .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+            .map(|(entity, _)| entity),
+    };
+    let Some(next) = next else {
+        return;
+    };
+    // Touch the interaction of both buttons so the button systems repaint them
+    for entity in focused.iter() {
+        commands.entity(entity).remove::<Focused>();
+        if let Ok(mut interaction) = interactions.get_mut(entity) {
+            interaction.set_changed();
+        }
+    }
+    commands.entity(next).insert(Focused);
+    if let Ok(mut interaction) = interactions.get_mut(next) {
+        interaction.set_changed();
+    }
+}
+
+// Click the focused button when the confirm action is pressed, and release it on the next
+// frame like a mouse click would
+fn click_focused(
+    actions: Actions,
+    focused: Query<Entity, (With<Focused>, With<Button>, Without<DisabledButton>)>,
+    mut interactions: Query<&mut Interaction>,
+    mut clicked: Local<Option<Entity>>,
+) {
+    if let Some(entity) = clicked.take() {
+        if let Ok(mut interaction) = interactions.get_mut(entity) {
+            interaction.set_if_neq(Interaction::None);
+        }
+    }
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+    if let Ok(entity) = focused.get_single() {
+        if let Ok(mut interaction) = interactions.get_mut(entity) {
+            *interaction = Interaction::Clicked;
+            *clicked = Some(entity);
+        }
+    }
+}
@@ -0,0 +1,686 @@
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use super::growth::Growth;
+use super::inventory::{Inventory, ItemRegistry};
+use super::save::{GameInProgress, PetSave};
+use super::shop::{ItemEffect, ItemKind};
+use super::sickness::Sickness;
+use super::GameState;
+
+// This plugin holds the pet model: its needs and how they wear off over time
+pub struct PetPlugin;
+
+impl Plugin for PetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PetDecay>()
+            .add_event::<PetCommand>()
+            .add_event::<PetFeedback>()
+            .add_system(pet_behaviour.in_set(OnUpdate(GameState::Game)))
+            // The pet keeps living on the menu screens while a game is in progress, the status
+            // screen shows it and the autosave counts that time as played
+            .add_systems(
+                (finish_activity, decay_stats, grow_older)
+                    .chain()
+                    .after(pet_behaviour)
+                    .distributive_run_if(resource_exists::<GameInProgress>()),
+            );
+    }
+}
+
+// Spawn the pet described by a save. It stays hidden until the game screen shows it
+pub fn spawn_pet(commands: &mut Commands, asset_server: &AssetServer, pet: &PetSave) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: asset_server.load(pet.profile.species.texture()),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Pet,
+            pet.profile.clone(),
+            pet.activity,
+            pet.stats,
+            pet.age,
+            pet.growth.clone(),
+            pet.sickness,
+        ))
+        .id()
+}
+
+// Finds out whether the mouse cursor is over the pet
+#[derive(SystemParam)]
+pub struct PetPicker<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    pets: Query<
+        'w,
+        's,
+        (
+            &'static GlobalTransform,
+            &'static TextureAtlasSprite,
+            &'static Handle<TextureAtlas>,
+            &'static ComputedVisibility,
+        ),
+        With<Pet>,
+    >,
+    atlases: Res<'w, Assets<TextureAtlas>>,
+}
+
+impl<'w, 's> PetPicker<'w, 's> {
+    // Position of the cursor in world coordinates, `None` when it is out of the window
+    pub fn cursor(&self) -> Option<Vec2> {
+        let cursor = self.windows.get_single().ok()?.cursor_position()?;
+        let (camera, camera_transform) = self.cameras.get_single().ok()?;
+        camera
+            .viewport_to_world(camera_transform, cursor)
+            .map(|ray| ray.origin.truncate())
+    }
+
+    // Area covered by the sprite of the pet in world coordinates, once it is shown and animated
+    pub fn pet_rect(&self) -> Option<Rect> {
+        let (transform, sprite, atlas, visibility) = self.pets.get_single().ok()?;
+        if !visibility.is_visible() {
+            return None;
+        }
+        let frame = self.atlases.get(atlas)?.textures.get(sprite.index)?;
+        let (scale, _, translation) = transform.to_scale_rotation_translation();
+        Some(Rect::from_center_size(
+            translation.truncate(),
+            frame.size() * scale.truncate(),
+        ))
+    }
+
+    pub fn cursor_on_pet(&self) -> bool {
+        match (self.cursor(), self.pet_rect()) {
+            (Some(cursor), Some(rect)) => rect.contains(cursor),
+            _ => false,
+        }
+    }
+}
+
+// Upper bound of every stat, a stat at this value is fully satisfied
+pub const STAT_MAX: f32 = 100.0;
+
+// Tag component used to find the pet entity
+#[derive(Component)]
+pub struct Pet;
+
+// Kinds of pets the player can adopt
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub enum Species {
+    #[default]
+    Turtle,
+    Mani,
+}
+
+impl Species {
+    pub const ALL: [Species; 2] = [Species::Turtle, Species::Mani];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Species::Turtle => "Turtle",
+            Species::Mani => "Mani",
+        }
+    }
+
+    pub fn texture(self) -> &'static str {
+        match self {
+            Species::Turtle => "textures/turtle-1.png",
+            Species::Mani => "textures/mani-idle-run.png",
+        }
+    }
+}
+
+// How hard a pet is to come by, which drives its price on the market
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    pub const ALL: [Rarity; 3] = [Rarity::Common, Rarity::Rare, Rarity::Legendary];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rarity::Common => "Common",
+            Rarity::Rare => "Rare",
+            Rarity::Legendary => "Legendary",
+        }
+    }
+
+    pub fn price_factor(self) -> u32 {
+        match self {
+            Rarity::Common => 1,
+            Rarity::Rare => 3,
+            Rarity::Legendary => 10,
+        }
+    }
+}
+
+// Who the pet is
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PetProfile {
+    pub name: String,
+    pub species: Species,
+    #[serde(default)]
+    pub rarity: Rarity,
+}
+
+impl Default for PetProfile {
+    fn default() -> Self {
+        PetProfile {
+            name: "Pet".to_string(),
+            species: Species::default(),
+            rarity: Rarity::default(),
+        }
+    }
+}
+
+// What the pet is currently doing
+#[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PetActivity {
+    #[default]
+    Idle,
+    Awake,
+    Sleeping,
+    Eating,
+    Playing,
+}
+
+impl PetActivity {
+    // Legal transitions of the activity state machine. A sleeping pet has to be woken up
+    // before doing anything else, and a pet can't fall asleep in the middle of a meal or a game
+    pub fn can_switch_to(self, next: PetActivity) -> bool {
+        use PetActivity::*;
+        match (self, next) {
+            (Sleeping, Awake) => true,
+            (Sleeping, _) => false,
+            (Eating, Idle | Awake) => true,
+            (Eating, _) => false,
+            (Playing, Idle | Awake | Eating) => true,
+            (Playing, _) => false,
+            (Idle | Awake, next) => next != self,
+        }
+    }
+
+    // How long the activity lasts before the pet goes back to `Awake`, `None` if it lasts
+    // until the player changes it
+    pub fn duration(self) -> Option<f32> {
+        match self {
+            PetActivity::Eating => Some(3.0),
+            PetActivity::Playing => Some(5.0),
+            _ => None,
+        }
+    }
+
+    // Multipliers applied to the decay rates while the pet does this activity. A negative
+    // multiplier makes the stat go up instead of down
+    pub fn decay_scale(self) -> DecayScale {
+        match self {
+            PetActivity::Idle => DecayScale::default(),
+            PetActivity::Awake => DecayScale {
+                energy: 1.5,
+                ..default()
+            },
+            PetActivity::Sleeping => DecayScale {
+                hunger: 0.5,
+                thirst: 0.5,
+                energy: -6.0,
+                happiness: 0.5,
+                ..default()
+            },
+            PetActivity::Eating => DecayScale {
+                hygiene: 2.0,
+                ..default()
+            },
+            PetActivity::Playing => DecayScale {
+                hunger: 1.5,
+                thirst: 2.0,
+                energy: 3.0,
+                happiness: -10.0,
+                hygiene: 2.0,
+            },
+        }
+    }
+
+    // Text shown to the player when the pet refuses to switch to `next`
+    fn refusal(self, next: PetActivity) -> String {
+        match (self, next) {
+            (PetActivity::Sleeping, _) => {
+                "Shh... your pet is sleeping, wake it up first".to_string()
+            }
+            (current, next) if current == next => {
+                format!("Your pet is already {}", current.label())
+            }
+            (current, next) => format!(
+                "Your pet can't stop {} to start {}",
+                current.label(),
+                next.label()
+            ),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PetActivity::Idle => "idle",
+            PetActivity::Awake => "awake",
+            PetActivity::Sleeping => "sleeping",
+            PetActivity::Eating => "eating",
+            PetActivity::Playing => "playing",
+        }
+    }
+}
+
+// Per-stat multipliers of `PetDecay`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecayScale {
+    pub hunger: f32,
+    pub thirst: f32,
+    pub energy: f32,
+    pub happiness: f32,
+    pub hygiene: f32,
+}
+
+impl Default for DecayScale {
+    fn default() -> Self {
+        DecayScale {
+            hunger: 1.0,
+            thirst: 1.0,
+            energy: 1.0,
+            happiness: 1.0,
+            hygiene: 1.0,
+        }
+    }
+}
+
+// Message for the player about the pet, like a refused command
+#[derive(Clone, Debug)]
+pub struct PetFeedback(pub String);
+
+// Time left before a timed activity (eating, playing) is over
+#[derive(Component, Deref, DerefMut)]
+pub struct ActivityTimer(Timer);
+
+// Orders given to the pet by the player
+#[derive(Clone, Debug, PartialEq)]
+pub enum PetCommand {
+    // Give the pet something to eat or drink from the inventory, by item id
+    Feed(String),
+    // Play with the pet, with the first toy of the inventory if there is one
+    Play,
+    // Use a care item or a medicine from the inventory, by item id
+    Treat(String),
+    Wake,
+    Sleep,
+    Idle,
+}
+
+impl PetCommand {
+    // Activity the pet has to switch to in order to follow the command, `None` when the pet
+    // can follow it whatever it is doing
+    fn activity(&self) -> Option<PetActivity> {
+        match self {
+            PetCommand::Feed(_) => Some(PetActivity::Eating),
+            PetCommand::Play => Some(PetActivity::Playing),
+            PetCommand::Treat(_) => None,
+            PetCommand::Wake => Some(PetActivity::Awake),
+            PetCommand::Sleep => Some(PetActivity::Sleeping),
+            PetCommand::Idle => Some(PetActivity::Idle),
+        }
+    }
+}
+
+// Time the pet has lived, in seconds of game time (including time simulated while the game
+// was closed)
+#[derive(
+    Component, Clone, Copy, Default, Debug, PartialEq, Deref, DerefMut, Serialize, Deserialize,
+)]
+pub struct PetAge(pub f32);
+
+// Every stat of `PetStats`, to go through them in order
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum Stat {
+    Hunger,
+    Thirst,
+    Energy,
+    Happiness,
+    Hygiene,
+    Health,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 6] = [
+        Stat::Hunger,
+        Stat::Thirst,
+        Stat::Energy,
+        Stat::Happiness,
+        Stat::Hygiene,
+        Stat::Health,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stat::Hunger => "Hunger",
+            Stat::Thirst => "Thirst",
+            Stat::Energy => "Energy",
+            Stat::Happiness => "Happiness",
+            Stat::Hygiene => "Hygiene",
+            Stat::Health => "Health",
+        }
+    }
+
+    pub fn value(self, stats: &PetStats) -> f32 {
+        match self {
+            Stat::Hunger => stats.hunger,
+            Stat::Thirst => stats.thirst,
+            Stat::Energy => stats.energy,
+            Stat::Happiness => stats.happiness,
+            Stat::Hygiene => stats.hygiene,
+            Stat::Health => stats.health,
+        }
+    }
+}
+
+// Needs of the pet. Every stat goes from 0 (critical) to `STAT_MAX` (fully satisfied)
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PetStats {
+    pub hunger: f32,
+    pub thirst: f32,
+    pub energy: f32,
+    pub happiness: f32,
+    pub hygiene: f32,
+    pub health: f32,
+}
+
+impl Default for PetStats {
+    fn default() -> Self {
+        PetStats {
+            hunger: STAT_MAX,
+            thirst: STAT_MAX,
+            energy: STAT_MAX,
+            happiness: STAT_MAX,
+            hygiene: STAT_MAX,
+            health: STAT_MAX,
+        }
+    }
+}
+
+impl PetStats {
+    // Keep every stat inside `0..=STAT_MAX`
+    pub fn clamp(&mut self) {
+        for stat in [
+            &mut self.hunger,
+            &mut self.thirst,
+            &mut self.energy,
+            &mut self.happiness,
+            &mut self.hygiene,
+            &mut self.health,
+        ] {
+            *stat = stat.clamp(0.0, STAT_MAX);
+        }
+    }
+
+    // How well the needs are met overall, from 0 (all empty) to 1 (all full)
+    pub fn care(&self) -> f32 {
+        Stat::ALL.iter().map(|stat| stat.value(self)).sum::<f32>()
+            / (Stat::ALL.len() as f32 * STAT_MAX)
+    }
+
+    // Keep every stat under the cap of the current growth stage
+    pub fn cap(&mut self, cap: f32) {
+        for stat in [
+            &mut self.hunger,
+            &mut self.thirst,
+            &mut self.energy,
+            &mut self.happiness,
+            &mut self.hygiene,
+            &mut self.health,
+        ] {
+            *stat = stat.min(cap);
+        }
+    }
+
+    // Raise the stats by what an item gives
+    pub fn apply(&mut self, effect: &ItemEffect) {
+        self.hunger += effect.hunger;
+        self.thirst += effect.thirst;
+        self.happiness += effect.happiness;
+        self.hygiene += effect.hygiene;
+        self.health += effect.health;
+        self.clamp();
+    }
+
+    // Number of needs (every stat but health) that are completely empty
+    pub fn depleted_needs(&self) -> usize {
+        [
+            self.hunger,
+            self.thirst,
+            self.energy,
+            self.happiness,
+            self.hygiene,
+        ]
+        .iter()
+        .filter(|stat| **stat <= 0.0)
+        .count()
+    }
+
+    // Wear the stats off for `seconds` of game time spent doing `activity`
+    pub fn decay(&mut self, decay: &PetDecay, activity: PetActivity, seconds: f32) {
+        let scale = activity.decay_scale();
+        self.hunger -= decay.hunger * scale.hunger * seconds;
+        self.thirst -= decay.thirst * scale.thirst * seconds;
+        self.energy -= decay.energy * scale.energy * seconds;
+        self.happiness -= decay.happiness * scale.happiness * seconds;
+        self.hygiene -= decay.hygiene * scale.hygiene * seconds;
+        // Health only goes down when the pet is neglected
+        self.health -= decay.neglect * self.depleted_needs() as f32 * seconds;
+        self.clamp();
+    }
+}
+
+// Decay rates of the pet stats, in points lost per second
+#[derive(Resource, Clone, Debug)]
+pub struct PetDecay {
+    pub hunger: f32,
+    pub thirst: f32,
+    pub energy: f32,
+    pub happiness: f32,
+    pub hygiene: f32,
+    // Health lost per second for each need that is empty
+    pub neglect: f32,
+}
+
+impl Default for PetDecay {
+    fn default() -> Self {
+        PetDecay {
+            hunger: 0.05,
+            thirst: 0.08,
+            energy: 0.03,
+            happiness: 0.04,
+            hygiene: 0.02,
+            neglect: 0.1,
+        }
+    }
+}
+
+// Apply the commands given by the player to the pet, refusing the ones the pet can't follow
+// in its current activity
+fn pet_behaviour(
+    mut commands: Commands,
+    mut pet_commands: EventReader<PetCommand>,
+    mut feedback: EventWriter<PetFeedback>,
+    registry: Res<ItemRegistry>,
+    mut inventory: ResMut<Inventory>,
+    mut pets: Query<
+        (
+            Entity,
+            &PetProfile,
+            &mut PetActivity,
+            &mut PetStats,
+            &mut Sickness,
+        ),
+        With<Pet>,
+    >,
+) {
+    for command in pet_commands.iter() {
+        for (pet, profile, mut activity, mut stats, mut sickness) in &mut pets {
+            let next = command.activity();
+            if let Some(next) = next.filter(|next| !activity.can_switch_to(*next)) {
+                feedback.send(PetFeedback(activity.refusal(next)));
+                continue;
+            }
+            if let (PetCommand::Play, Some(ailment)) = (command, sickness.ailment) {
+                feedback.send(PetFeedback(format!(
+                    "{} is too sick to play, it has {}",
+                    profile.name,
+                    ailment.name()
+                )));
+                continue;
+            }
+            // Items are only used up once the pet agreed to follow the command
+            let item = match command {
+                PetCommand::Feed(id) | PetCommand::Treat(id) => {
+                    let Some(item) = registry.get(id) else {
+                        continue;
+                    };
+                    if inventory.remove(id, 1).is_err() {
+                        feedback.send(PetFeedback(format!(
+                            "You don't have any {} left",
+                            item.name
+                        )));
+                        continue;
+                    }
+                    Some(item)
+                }
+                PetCommand::Play => {
+                    let toy = inventory.items().into_iter().find_map(|(id, _)| {
+                        registry.get(id).filter(|item| item.kind == ItemKind::Toy)
+                    });
+                    if let Some(toy) = toy {
+                        inventory.remove(&toy.id, 1).ok();
+                        feedback.send(PetFeedback(format!(
+                            "{} loves the {}!",
+                            profile.name, toy.name
+                        )));
+                    }
+                    toy
+                }
+                _ => None,
+            };
+            if let Some(item) = item {
+                stats.apply(&item.effect);
+                if let Some(ailment) = sickness.treat(&item.cures) {
+                    feedback.send(PetFeedback(format!(
+                        "{} is cured of {}!",
+                        profile.name,
+                        ailment.name()
+                    )));
+                }
+            }
+            if let Some(next) = next {
+                exit_activity(&mut commands, pet, *activity);
+                enter_activity(&mut commands, pet, next, &mut stats);
+                *activity = next;
+            }
+        }
+    }
+}
+
+// Hook run when the pet starts an activity
+fn enter_activity(
+    commands: &mut Commands,
+    pet: Entity,
+    activity: PetActivity,
+    stats: &mut PetStats,
+) {
+    if let Some(seconds) = activity.duration() {
+        commands
+            .entity(pet)
+            .insert(ActivityTimer(Timer::from_seconds(seconds, TimerMode::Once)));
+    }
+    if activity == PetActivity::Playing {
+        stats.happiness += 10.0;
+        stats.clamp();
+    }
+}
+
+// Hook run when the pet stops an activity
+fn exit_activity(commands: &mut Commands, pet: Entity, activity: PetActivity) {
+    if activity.duration().is_some() {
+        commands.entity(pet).remove::<ActivityTimer>();
+    }
+}
+
+// Send the pet back to `Awake` once a timed activity is over
+fn finish_activity(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pets: Query<(Entity, &mut PetActivity, &mut ActivityTimer), With<Pet>>,
+) {
+    for (pet, mut activity, mut timer) in &mut pets {
+        if timer.tick(time.delta()).just_finished() {
+            exit_activity(&mut commands, pet, *activity);
+            *activity = PetActivity::Awake;
+        }
+    }
+}
+
+fn grow_older(time: Res<Time>, mut pets: Query<&mut PetAge, With<Pet>>) {
+    for mut age in &mut pets {
+        **age += time.delta_seconds();
+    }
+}
+
+// Make the pet needs wear off while the game is running
+fn decay_stats(
+    time: Res<Time>,
+    decay: Res<PetDecay>,
+    mut pets: Query<(&PetActivity, &mut PetStats, &Growth), With<Pet>>,
+) {
+    for (activity, mut stats, growth) in &mut pets {
+        stats.decay(&decay, *activity, time.delta_seconds() * growth.decay_rate);
+        stats.cap(growth.stat_cap);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_switches() {
+        use PetActivity::*;
+        assert!(Idle.can_switch_to(Sleeping));
+        assert!(Awake.can_switch_to(Playing));
+        assert!(Sleeping.can_switch_to(Awake));
+        assert!(Eating.can_switch_to(Idle));
+        assert!(Playing.can_switch_to(Eating));
+    }
+
+    #[test]
+    fn refused_switches() {
+        use PetActivity::*;
+        assert!(!Idle.can_switch_to(Idle));
+        assert!(!Sleeping.can_switch_to(Eating));
+        assert!(!Sleeping.can_switch_to(Playing));
+        assert!(!Eating.can_switch_to(Sleeping));
+        assert!(!Playing.can_switch_to(Sleeping));
+    }
+
+    #[test]
+    fn decay_stops_at_zero() {
+        let mut stats = PetStats::default();
+        stats.decay(&PetDecay::default(), PetActivity::Playing, 1.0e6);
+        assert_eq!(stats.hunger, 0.0);
+        assert_eq!(stats.thirst, 0.0);
+        assert_eq!(stats.energy, 0.0);
+        assert_eq!(stats.hygiene, 0.0);
+        assert_eq!(stats.health, 0.0);
+        // Playing makes the pet happier, up to the maximum
+        assert_eq!(stats.happiness, STAT_MAX);
+    }
+}
@@ -0,0 +1,574 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::growth::Growth;
+use super::inventory::Inventory;
+use super::market::{Ledger, Transaction, Wallet, STARTING_MONEY};
+use super::pet::{spawn_pet, Pet, PetActivity, PetAge, PetDecay, PetProfile, PetStats};
+use super::sickness::{Fate, Graveyard, Memorial, MemorialPending, Sickness};
+use super::GameState;
+
+// This plugin loads the save when the player continues the game and writes it back to disk: on
+// a timer, when leaving the game screen and when the app is closed
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AutosaveTimer(Timer::from_seconds(
+            30.0,
+            TimerMode::Repeating,
+        )))
+        .init_resource::<OfflineProgress>()
+        .add_systems((
+            autosave.run_if(resource_exists::<GameInProgress>()),
+            save_game
+                .run_if(resource_exists::<GameInProgress>())
+                .in_schedule(OnExit(GameState::Game)),
+            save_on_exit
+                .run_if(resource_exists::<GameInProgress>())
+                .in_base_set(CoreSet::Last),
+        ));
+    }
+}
+
+// Version of the save file layout, bumped every time it changes. Older saves are read with the
+// defaults of what they lack, newer ones are refused so that no field is silently dropped:
+// 1. stats and activity of the pet
+// 2. name and species of the pet
+// 3. age of the pet
+// 4. wallet and ledger, the pet can be sold
+// 5. inventory
+// 6. growth of the pet
+// 7. sickness of the pet and memorials of the pets that left
+pub const SAVE_VERSION: u32 = 7;
+
+// Everything that is written to the save file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveFile {
+    pub version: u32,
+    // Unix timestamp (in seconds) of the moment the game was saved
+    pub saved_at: u64,
+    // `None` once the pet was sold, until a new one is bought. Saves before version 4 always
+    // have one
+    pub pet: Option<PetSave>,
+    // Saves before version 4 start with the money of a new game
+    #[serde(default = "starting_money")]
+    pub money: u32,
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+    // Saves before version 5 get the items of a new game
+    #[serde(default = "Inventory::starter")]
+    pub inventory: Inventory,
+    // Pets that died or ran away during this game, since version 7
+    #[serde(default)]
+    pub memorials: Vec<Memorial>,
+}
+
+fn starting_money() -> u32 {
+    STARTING_MONEY
+}
+
+impl SaveFile {
+    // Save of a game that just started, with a brand new pet
+    pub fn new_game(profile: PetProfile) -> Self {
+        SaveFile {
+            version: SAVE_VERSION,
+            saved_at: now(),
+            pet: Some(PetSave {
+                profile,
+                ..default()
+            }),
+            money: STARTING_MONEY,
+            transactions: Vec::new(),
+            inventory: Inventory::starter(),
+            memorials: Vec::new(),
+        }
+    }
+}
+
+// The saved state of the pet
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PetSave {
+    // Saves before version 2 get the default name and species
+    #[serde(default)]
+    pub profile: PetProfile,
+    pub stats: PetStats,
+    pub activity: PetActivity,
+    // Pets of saves before version 3 start at age 0
+    #[serde(default)]
+    pub age: PetAge,
+    // Pets of saves before version 6 start as eggs, and grow up to the stage of their age
+    #[serde(default)]
+    pub growth: Growth,
+    // Pets of saves before version 7 are healthy
+    #[serde(default)]
+    pub sickness: Sickness,
+}
+
+// Present once a game was loaded or started, so there is something to write back to disk
+#[derive(Resource)]
+pub struct GameInProgress;
+
+#[derive(Resource, Deref, DerefMut)]
+struct AutosaveTimer(Timer);
+
+// How the time spent with the game closed is simulated when a save is loaded
+#[derive(Resource, Clone, Debug)]
+pub struct OfflineProgress {
+    // Length of one simulation step, in seconds. Anything under `MIN_OFFLINE_STEP` is raised
+    // to it
+    pub step: f32,
+    // Longest absence that is simulated, in seconds. Anything above is ignored
+    pub max_away: u64,
+    // Absences shorter than this (in seconds) don't get a summary screen
+    pub min_summary: u64,
+}
+
+// Shortest simulation step, a step of zero would never get through the absence
+const MIN_OFFLINE_STEP: f32 = 1.0;
+
+impl Default for OfflineProgress {
+    fn default() -> Self {
+        OfflineProgress {
+            step: 60.0,
+            max_away: 24 * 60 * 60,
+            min_summary: 60,
+        }
+    }
+}
+
+// What happened to the pet while the game was closed
+#[derive(Resource, Clone, Debug)]
+pub struct AwaySummary {
+    // Real time since the last save, in seconds
+    pub away: u64,
+    // Part of that time that was simulated, in seconds
+    pub simulated: u64,
+    pub before: PetStats,
+    pub after: PetStats,
+    // Set when the pet didn't make it through the absence
+    pub fate: Option<Fate>,
+}
+
+impl AwaySummary {
+    pub fn worth_showing(&self, offline: &OfflineProgress) -> bool {
+        self.away >= offline.min_summary
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    NoDataDir,
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    // The save was written by a newer version of the game
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::NoDataDir => write!(f, "no user data directory on this system"),
+            SaveError::Io(err) => write!(f, "{err}"),
+            SaveError::Parse(err) => write!(f, "corrupt save file: {err}"),
+            SaveError::Serialize(err) => write!(f, "{err}"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "save version {version} is newer than supported version {SAVE_VERSION}"
+                )
+            }
+        }
+    }
+}
+
+// Location of the save file, under the user data directory
+pub fn save_path() -> Result<PathBuf, SaveError> {
+    dirs::data_dir()
+        .map(|dir| dir.join("pet_fun").join("save.ron"))
+        .ok_or(SaveError::NoDataDir)
+}
+
+pub fn save_exists() -> bool {
+    save_path().is_ok_and(|path| path.is_file())
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+pub fn load() -> Result<SaveFile, SaveError> {
+    let text = fs::read_to_string(save_path()?).map_err(SaveError::Io)?;
+    parse(&text)
+}
+
+fn parse(text: &str) -> Result<SaveFile, SaveError> {
+    // Saves made before the pet could be sold don't wrap it in `Some(...)`
+    let save: SaveFile = ron::Options::default()
+        .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+        .from_str(text)
+        .map_err(SaveError::Parse)?;
+    if save.version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(save.version));
+    }
+    Ok(save)
+}
+
+// Decay the pet stats for the time elapsed between `save.saved_at` and `now`, in steps of
+// `offline.step` seconds and up to `offline.max_away` seconds
+pub fn fast_forward(
+    save: &mut SaveFile,
+    decay: &PetDecay,
+    offline: &OfflineProgress,
+    now: u64,
+) -> Option<AwaySummary> {
+    let away = now.saturating_sub(save.saved_at);
+    let simulated = away.min(offline.max_away);
+    save.saved_at = now;
+    let pet = save.pet.as_mut()?;
+    let before = pet.stats;
+    let mut remaining = simulated as f32;
+    let mut fate = None;
+    let step_len = offline.step.max(MIN_OFFLINE_STEP);
+    while remaining > 0.0 && fate.is_none() {
+        let step = remaining.min(step_len);
+        pet.stats
+            .decay(decay, pet.activity, step * pet.growth.decay_rate);
+        pet.stats.cap(pet.growth.stat_cap);
+        pet.growth.track(&pet.stats, step);
+        fate = pet.sickness.progress(&mut pet.stats, step);
+        pet.age.0 += step;
+        remaining -= step;
+    }
+    let after = pet.stats;
+    // The pet is gone, only its memorial stays in the save
+    if let Some(fate) = fate {
+        let memorial = Memorial::new(&pet.profile, &pet.age, &pet.growth, fate);
+        save.memorials.push(memorial);
+        save.pet = None;
+    }
+    Some(AwaySummary {
+        away,
+        simulated,
+        before,
+        after,
+        fate,
+    })
+}
+
+// Put the game described by `save` in the world: the pet (hidden until the game screen is
+// shown), the wallet, the ledger and the inventory. Any pet already there is replaced
+pub fn restore(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    pets: &Query<Entity, With<Pet>>,
+    save: SaveFile,
+) {
+    for pet in pets {
+        commands.entity(pet).despawn_recursive();
+    }
+    if let Some(pet) = &save.pet {
+        spawn_pet(commands, asset_server, pet);
+    }
+    commands.insert_resource(Wallet(save.money));
+    commands.insert_resource(Ledger(save.transactions));
+    commands.insert_resource(save.inventory);
+    commands.insert_resource(Graveyard(save.memorials));
+    commands.insert_resource(GameInProgress);
+}
+
+pub fn write(save: &SaveFile) -> Result<(), SaveError> {
+    let path = save_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(SaveError::Io)?;
+    }
+    let text = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())
+        .map_err(SaveError::Serialize)?;
+    write_atomically(&path, &text).map_err(SaveError::Io)
+}
+
+// Write next to `path` first and move the file in place, so a crash while writing can't leave
+// a truncated file instead of the previous one
+pub fn write_atomically(path: &Path, text: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("ron.tmp");
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path)
+}
+
+// Move the save out of the way before a new game overwrites it, so the old pets aren't lost
+pub fn archive() -> Result<(), SaveError> {
+    let path = save_path()?;
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+    let archive = dir.join("archive");
+    fs::create_dir_all(&archive).map_err(SaveError::Io)?;
+    fs::rename(&path, archive.join(format!("save-{}.ron", now()))).map_err(SaveError::Io)
+}
+
+// What loading the save brought up, to pick the screen to show next
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resumed {
+    // The pet is back and the game can go on
+    Pet,
+    // The pet is back after a long absence, what happened is in `AwaySummary`
+    Away,
+    // The pet was sold before the game was closed
+    NoPet,
+    // The pet died or ran away while the game was closed, its memorial is pending
+    PetLeft,
+}
+
+// Loads the save into the world once the player picks something that needs it: Continue, or
+// a screen showing the saved game
+#[derive(SystemParam)]
+pub struct SaveLoader<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    decay: Res<'w, PetDecay>,
+    offline: Res<'w, OfflineProgress>,
+    game: Option<Res<'w, GameInProgress>>,
+    pets: Query<'w, 's, Entity, With<Pet>>,
+}
+
+impl<'w, 's> SaveLoader<'w, 's> {
+    // Whether loading would do anything: there is a save and no game going yet
+    pub fn pending(&self) -> bool {
+        self.game.is_none() && save_exists()
+    }
+
+    // Load the save and simulate the time the game was closed, so the pet lives on from where
+    // it was left. Returns `None` when there is nothing to load
+    pub fn load(&mut self) -> Result<Option<Resumed>, SaveError> {
+        if !self.pending() {
+            return Ok(None);
+        }
+        let mut save = load()?;
+        // The pet kept living while the game was closed
+        let resumed = match fast_forward(&mut save, &self.decay, &self.offline, now()) {
+            None => Resumed::NoPet,
+            Some(summary) if summary.fate.is_some() => {
+                self.commands.insert_resource(MemorialPending);
+                Resumed::PetLeft
+            }
+            Some(summary) if summary.worth_showing(&self.offline) => {
+                self.commands.insert_resource(summary);
+                Resumed::Away
+            }
+            Some(_) => Resumed::Pet,
+        };
+        restore(&mut self.commands, &self.asset_server, &self.pets, save);
+        Ok(Some(resumed))
+    }
+}
+
+// Components of the pet entity that end up in the save
+type PetQuery<'a> = (
+    &'a PetProfile,
+    &'a PetStats,
+    &'a PetActivity,
+    &'a PetAge,
+    &'a Growth,
+    &'a Sickness,
+);
+
+// Everything `snapshot` reads from the world
+#[derive(SystemParam)]
+struct GameData<'w, 's> {
+    pets: Query<'w, 's, PetQuery<'static>, With<Pet>>,
+    wallet: Res<'w, Wallet>,
+    ledger: Res<'w, Ledger>,
+    inventory: Res<'w, Inventory>,
+    graveyard: Res<'w, Graveyard>,
+}
+
+// Build the save file from the world
+fn snapshot(game: &GameData) -> SaveFile {
+    let pet =
+        game.pets
+            .get_single()
+            .ok()
+            .map(|(profile, stats, activity, age, growth, sickness)| {
+                // Eating and playing are over by the time the game is loaded again
+                let activity = match activity.duration() {
+                    Some(_) => PetActivity::Awake,
+                    None => *activity,
+                };
+                PetSave {
+                    profile: profile.clone(),
+                    stats: *stats,
+                    activity,
+                    age: *age,
+                    growth: growth.clone(),
+                    sickness: *sickness,
+                }
+            });
+    SaveFile {
+        version: SAVE_VERSION,
+        saved_at: now(),
+        pet,
+        money: game.wallet.0,
+        transactions: game.ledger.0.clone(),
+        inventory: game.inventory.clone(),
+        memorials: game.graveyard.0.clone(),
+    }
+}
+
+fn save_game(game: GameData) {
+    if let Err(err) = write(&snapshot(&game)) {
+        error!("Failed to save the game: {err}");
+    }
+}
+
+fn autosave(time: Res<Time>, mut timer: ResMut<AutosaveTimer>, game: GameData) {
+    if timer.tick(time.delta()).just_finished() {
+        save_game(game);
+    }
+}
+
+fn save_on_exit(mut app_exit_events: EventReader<AppExit>, game: GameData) {
+    if app_exit_events.iter().last().is_some() {
+        save_game(game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pet::Stat;
+    use crate::shop::{ItemDef, ItemEffect, ItemKind};
+
+    fn save_at(saved_at: u64, pet: PetSave) -> SaveFile {
+        SaveFile {
+            saved_at,
+            pet: Some(pet),
+            ..SaveFile::new_game(PetProfile::default())
+        }
+    }
+
+    fn offline(step: f32, max_away: u64) -> OfflineProgress {
+        OfflineProgress {
+            step,
+            max_away,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn simulates_up_to_max_away() {
+        let mut save = save_at(1_000, PetSave::default());
+        let summary =
+            fast_forward(&mut save, &PetDecay::default(), &offline(60.0, 600), 5_000).unwrap();
+        assert_eq!(summary.away, 4_000);
+        assert_eq!(summary.simulated, 600);
+        assert_eq!(save.saved_at, 5_000);
+        assert_eq!(save.pet.unwrap().age.0, 600.0);
+    }
+
+    #[test]
+    fn steps_add_up_to_the_absence() {
+        let decay = PetDecay::default();
+        let pet = PetSave::default();
+        // 150 seconds is two full steps and a shorter one
+        let mut save = save_at(0, pet.clone());
+        let summary = fast_forward(&mut save, &decay, &offline(60.0, 3_600), 150).unwrap();
+        let mut expected = pet.stats;
+        expected.decay(&decay, pet.activity, 150.0);
+        let after = save.pet.unwrap();
+        assert_eq!(after.age.0, 150.0);
+        for stat in Stat::ALL {
+            assert!((stat.value(&after.stats) - stat.value(&expected)).abs() < 1e-3);
+        }
+        assert_eq!(summary.after, after.stats);
+    }
+
+    #[test]
+    fn stops_when_the_pet_dies() {
+        // A pet with nothing left dies after an hour, well before the end of the absence
+        let pet = PetSave {
+            stats: PetStats {
+                hunger: 0.0,
+                thirst: 0.0,
+                energy: 0.0,
+                happiness: 0.0,
+                hygiene: 0.0,
+                health: 0.0,
+            },
+            ..default()
+        };
+        let mut save = save_at(0, pet);
+        let summary = fast_forward(
+            &mut save,
+            &PetDecay::default(),
+            &offline(60.0, 86_400),
+            86_400,
+        )
+        .unwrap();
+        assert_eq!(summary.fate, Some(Fate::Died));
+        assert!(save.pet.is_none());
+        let memorial = &save.memorials[0];
+        assert_eq!(memorial.fate, Fate::Died);
+        assert!(memorial.age < 2.0 * 3_600.0, "died at {}", memorial.age);
+    }
+
+    #[test]
+    fn zero_step_still_ends() {
+        let mut save = save_at(0, PetSave::default());
+        fast_forward(&mut save, &PetDecay::default(), &offline(0.0, 600), 600).unwrap();
+        assert_eq!(save.pet.unwrap().age.0, 600.0);
+    }
+
+    #[test]
+    fn inventory_round_trips_through_the_save() {
+        let mut save = SaveFile::new_game(PetProfile::default());
+        let ball = ItemDef {
+            id: "ball".to_string(),
+            name: "Ball".to_string(),
+            kind: ItemKind::Toy,
+            price: 5,
+            max_stack: 3,
+            effect: ItemEffect::default(),
+            cures: Vec::new(),
+        };
+        save.inventory.add(&ball, 4);
+        let text = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default()).unwrap();
+        let parsed = parse(&text).unwrap();
+        assert_eq!(parsed.inventory, save.inventory);
+        assert_eq!(parsed.inventory.count("ball"), 4);
+    }
+
+    #[test]
+    fn old_saves_get_the_starter_inventory() {
+        // Version 1 had a bare pet and no money, version 4 had money but no inventory
+        let v1 = "(version: 1, saved_at: 10, pet: (stats: (hunger: 50.0, thirst: 50.0, \
+                  energy: 50.0, happiness: 50.0, hygiene: 50.0, health: 50.0), activity: Awake))";
+        let v4 = "(version: 4, saved_at: 10, pet: None, money: 40, transactions: [])";
+        for text in [v1, v4] {
+            let save = parse(text).unwrap();
+            assert_eq!(save.inventory, Inventory::starter());
+        }
+        let v1 = parse(v1).unwrap();
+        assert_eq!(v1.money, STARTING_MONEY);
+        let pet = v1.pet.unwrap();
+        assert_eq!(pet.stats.hunger, 50.0);
+        assert_eq!(pet.growth, Growth::default());
+        assert_eq!(parse(v4).unwrap().money, 40);
+    }
+
+    #[test]
+    fn newer_saves_are_refused() {
+        let text = format!("(version: {}, saved_at: 10, pet: None)", SAVE_VERSION + 1);
+        assert!(matches!(
+            parse(&text),
+            Err(SaveError::UnsupportedVersion(version)) if version == SAVE_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,322 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::{audio::AudioSink, prelude::*, window::PrimaryWindow, window::WindowLevel};
+use serde::{Deserialize, Serialize};
+
+use super::actions::InputBindings;
+use super::save::write_atomically;
+
+// This plugin loads the settings from the config file, applies them to the app as soon as they
+// change and writes them back to disk
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = load().unwrap_or_else(|err| {
+            warn!("Using default settings: {err}");
+            SettingsFile::default()
+        });
+        app.insert_resource(settings.display_quality)
+            .insert_resource(settings.volume)
+            .insert_resource(settings.window_scale)
+            .insert_resource(settings.always_on_top)
+            .insert_resource(settings.widget_mode)
+            .insert_resource(settings.theme)
+            .insert_resource(settings.show_splash)
+            .insert_resource(settings.bindings)
+            .insert_resource(WindowPlacement(
+                settings.window_position.map(|(x, y)| IVec2::new(x, y)),
+            ))
+            .add_systems((apply_settings, apply_volume, save_settings));
+    }
+}
+
+// Rendering quality. Like every setting, it is a resource in the app and a component on the
+// buttons of the settings screen
+#[derive(
+    Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum DisplayQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl DisplayQuality {
+    pub const ALL: [DisplayQuality; 3] = [
+        DisplayQuality::Low,
+        DisplayQuality::Medium,
+        DisplayQuality::High,
+    ];
+
+    fn msaa(self) -> Msaa {
+        match self {
+            DisplayQuality::Low => Msaa::Off,
+            DisplayQuality::Medium => Msaa::Sample4,
+            DisplayQuality::High => Msaa::Sample8,
+        }
+    }
+}
+
+// Volume of the game sounds, from 0 (muted) to 9
+#[derive(Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Volume(pub u32);
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume(7)
+    }
+}
+
+impl Volume {
+    pub const MAX: u32 = 9;
+
+    // Volume to give to an audio sink
+    pub fn level(self) -> f32 {
+        self.0 as f32 / Self::MAX as f32
+    }
+}
+
+// Scale factor of the window, to make the pet and the menus bigger on large screens
+#[derive(
+    Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum WindowScale {
+    #[default]
+    Normal,
+    Large,
+    Huge,
+}
+
+impl WindowScale {
+    pub const ALL: [WindowScale; 3] = [WindowScale::Normal, WindowScale::Large, WindowScale::Huge];
+
+    pub fn factor(self) -> f64 {
+        match self {
+            WindowScale::Normal => 1.0,
+            WindowScale::Large => 1.5,
+            WindowScale::Huge => 2.0,
+        }
+    }
+}
+
+// Whether the window stays above all other windows
+#[derive(
+    Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub struct AlwaysOnTop(pub bool);
+
+// Whether the window is shown as a desktop widget: no decorations and just big enough for the
+// pet while playing
+#[derive(
+    Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub struct WidgetMode(pub bool);
+
+// Whether the splash screen is shown on start, the `--no-splash` flag turns it off for one run
+#[derive(Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct ShowSplash(pub bool);
+
+impl Default for ShowSplash {
+    fn default() -> Self {
+        ShowSplash(true)
+    }
+}
+
+// Look of the menus, each preset is described in `assets/ui/<file name>.theme.ron`
+#[derive(
+    Resource, Component, Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub const ALL: [ThemePreset; 3] = [
+        ThemePreset::Dark,
+        ThemePreset::Light,
+        ThemePreset::HighContrast,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemePreset::Dark => "Dark",
+            ThemePreset::Light => "Light",
+            ThemePreset::HighContrast => "Contrast",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ThemePreset::Dark => "dark",
+            ThemePreset::Light => "light",
+            ThemePreset::HighContrast => "high_contrast",
+        }
+    }
+}
+
+// Last known position of the window on the screen, in physical pixels
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct WindowPlacement(pub Option<IVec2>);
+
+// Everything that is written to the config file
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SettingsFile {
+    pub display_quality: DisplayQuality,
+    pub volume: Volume,
+    pub window_scale: WindowScale,
+    pub always_on_top: AlwaysOnTop,
+    pub widget_mode: WidgetMode,
+    pub theme: ThemePreset,
+    pub show_splash: ShowSplash,
+    pub window_position: Option<(i32, i32)>,
+    pub bindings: InputBindings,
+}
+
+impl SettingsFile {
+    // Bring the values edited by hand back into the range the settings screen offers
+    fn validated(mut self) -> Self {
+        if self.volume.0 > Volume::MAX {
+            warn!(
+                "Volume {} is too loud, using {}",
+                self.volume.0,
+                Volume::MAX
+            );
+            self.volume = Volume(Volume::MAX);
+        }
+        self
+    }
+}
+
+// Location of the config file, under the user config directory. It is kept apart from the
+// save so that starting a new game doesn't reset the settings
+pub fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("pet_fun").join("settings.ron"))
+}
+
+fn load() -> Result<SettingsFile, String> {
+    let path = settings_path().ok_or("no user config directory on this system")?;
+    if !path.is_file() {
+        return Ok(SettingsFile::default());
+    }
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let settings: SettingsFile = ron::from_str(&text).map_err(|err| err.to_string())?;
+    Ok(settings.validated())
+}
+
+fn write(settings: &SettingsFile) -> Result<(), String> {
+    let path = settings_path().ok_or("no user config directory on this system")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let text = ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    write_atomically(&path, &text).map_err(|err| err.to_string())
+}
+
+// Apply the settings to the window and the renderer when they change
+fn apply_settings(
+    mut commands: Commands,
+    display_quality: Res<DisplayQuality>,
+    window_scale: Res<WindowScale>,
+    always_on_top: Res<AlwaysOnTop>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if display_quality.is_changed() {
+        commands.insert_resource(display_quality.msaa());
+    }
+    if window_scale.is_changed() || always_on_top.is_changed() {
+        for mut window in &mut windows {
+            window
+                .resolution
+                .set_scale_factor_override(Some(window_scale.factor()));
+            window.window_level = if always_on_top.0 {
+                WindowLevel::AlwaysOnTop
+            } else {
+                WindowLevel::Normal
+            };
+        }
+    }
+}
+
+// Give every sound the volume of the settings: all of them when it changes, and each new one
+// as soon as it starts playing, whatever system played it
+fn apply_volume(
+    volume: Res<Volume>,
+    mut sink_events: EventReader<AssetEvent<AudioSink>>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    if volume.is_changed() {
+        for (_, sink) in audio_sinks.iter() {
+            sink.set_volume(volume.level());
+        }
+    }
+    for event in sink_events.iter() {
+        if let AssetEvent::Created { handle } = event {
+            if let Some(sink) = audio_sinks.get(handle) {
+                sink.set_volume(volume.level());
+            }
+        }
+    }
+}
+
+// Write the settings to the config file every time one of them changes
+#[allow(clippy::too_many_arguments)]
+fn save_settings(
+    display_quality: Res<DisplayQuality>,
+    volume: Res<Volume>,
+    window_scale: Res<WindowScale>,
+    always_on_top: Res<AlwaysOnTop>,
+    widget_mode: Res<WidgetMode>,
+    theme: Res<ThemePreset>,
+    show_splash: Res<ShowSplash>,
+    window_placement: Res<WindowPlacement>,
+    bindings: Res<InputBindings>,
+) {
+    let changed = display_quality.is_changed()
+        || volume.is_changed()
+        || window_scale.is_changed()
+        || always_on_top.is_changed()
+        || widget_mode.is_changed()
+        || theme.is_changed()
+        || show_splash.is_changed()
+        || window_placement.is_changed()
+        || bindings.is_changed();
+    // Resources are "changed" on the first frame too, there is nothing new to write then
+    let added = display_quality.is_added();
+    if changed && !added {
+        let settings = SettingsFile {
+            display_quality: *display_quality,
+            volume: *volume,
+            window_scale: *window_scale,
+            always_on_top: *always_on_top,
+            widget_mode: *widget_mode,
+            theme: *theme,
+            show_splash: *show_splash,
+            window_position: window_placement.0.map(|position| (position.x, position.y)),
+            bindings: bindings.clone(),
+        };
+        if let Err(err) = write(&settings) {
+            error!("Failed to save the settings: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loud_volume_is_clamped() {
+        let settings: SettingsFile = ron::from_str("(volume: (12))").unwrap();
+        assert_eq!(settings.validated().volume, Volume(Volume::MAX));
+        let settings: SettingsFile = ron::from_str("(volume: (3))").unwrap();
+        assert_eq!(settings.validated().volume, Volume(3));
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use super::loading::RonLoader;
+use super::sickness::Ailment;
+
+// This plugin loads the catalogue of the vendor from `assets/data/items.catalogue.ron`
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ItemCatalogue>()
+            .add_asset_loader(RonLoader::<ItemCatalogue>::new(&["catalogue.ron"]))
+            .add_startup_system(load_catalogue);
+    }
+}
+
+// Every item sold by the vendor, as read from the data file
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "c2f6a0d4-8b3e-4f71-a5c9-6e1d7b2f9a48"]
+#[serde(transparent)]
+pub struct ItemCatalogue(pub Vec<ItemDef>);
+
+// An item that can be bought and given to the pet
+#[derive(Deserialize, Clone, Debug)]
+pub struct ItemDef {
+    pub id: String,
+    pub name: String,
+    pub kind: ItemKind,
+    pub price: u32,
+    // Most of this item a single inventory slot can hold
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub effect: ItemEffect,
+    // Ailments healed by this item
+    #[serde(default)]
+    pub cures: Vec<Ailment>,
+}
+
+fn default_max_stack() -> u32 {
+    20
+}
+
+#[derive(Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ItemKind {
+    Food,
+    Drink,
+    Toy,
+    Care,
+    Medicine,
+}
+
+impl ItemKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemKind::Food => "Food",
+            ItemKind::Drink => "Drink",
+            ItemKind::Toy => "Toy",
+            ItemKind::Care => "Care",
+            ItemKind::Medicine => "Medicine",
+        }
+    }
+}
+
+// How much an item raises each stat of the pet when it is used
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct ItemEffect {
+    pub hunger: f32,
+    pub thirst: f32,
+    pub happiness: f32,
+    pub hygiene: f32,
+    pub health: f32,
+}
+
+impl ItemEffect {
+    // Short description like "+30 hunger +15 thirst", skipping the stats it doesn't touch
+    pub fn summary(&self) -> String {
+        [
+            (self.hunger, "hunger"),
+            (self.thirst, "thirst"),
+            (self.happiness, "happiness"),
+            (self.hygiene, "hygiene"),
+            (self.health, "health"),
+        ]
+        .iter()
+        .filter(|(amount, _)| *amount != 0.0)
+        .map(|(amount, stat)| format!("{amount:+} {stat}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct CatalogueHandle(Handle<ItemCatalogue>);
+
+pub const CATALOGUE_PATH: &str = "data/items.catalogue.ron";
+
+fn load_catalogue(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CatalogueHandle(asset_server.load(CATALOGUE_PATH)));
+}
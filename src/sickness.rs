@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::growth::Growth;
+use super::pet::{Pet, PetAge, PetFeedback, PetProfile, PetStats, Species};
+use super::save::{now, GameInProgress};
+use super::GameState;
+
+// This plugin makes neglected pets fall sick, and eventually die or run away
+pub struct SicknessPlugin;
+
+impl Plugin for SicknessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Graveyard>()
+            .add_system(check_health.run_if(resource_exists::<GameInProgress>()));
+    }
+}
+
+// The pet catches fleas when its hygiene drops under this value
+const FLEAS_BELOW_HYGIENE: f32 = 15.0;
+// The pet catches the flu when its health drops under this value
+const FLU_BELOW_HEALTH: f32 = 30.0;
+// Seconds the pet survives with no health left
+const DIES_AFTER: f32 = 60.0 * 60.0;
+// Seconds the pet stays with no happiness left before running away
+const RUNS_AWAY_AFTER: f32 = 6.0 * 60.0 * 60.0;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Ailment {
+    Fleas,
+    Flu,
+}
+
+impl Ailment {
+    pub fn name(self) -> &'static str {
+        match self {
+            Ailment::Fleas => "fleas",
+            Ailment::Flu => "the flu",
+        }
+    }
+
+    // Stats lost per second while the pet has this ailment
+    fn wear(self, stats: &mut PetStats, seconds: f32) {
+        match self {
+            Ailment::Fleas => {
+                stats.happiness -= 0.1 * seconds;
+                stats.health -= 0.02 * seconds;
+            }
+            Ailment::Flu => {
+                stats.energy -= 0.05 * seconds;
+                stats.health -= 0.05 * seconds;
+            }
+        }
+        stats.clamp();
+    }
+}
+
+// How the life of a pet ended
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Fate {
+    Died,
+    RanAway,
+}
+
+// Health of the pet, saved with it
+#[derive(Component, Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sickness {
+    pub ailment: Option<Ailment>,
+    // Seconds spent with no health left
+    pub dying_for: f32,
+    // Seconds spent with no happiness left
+    pub miserable_for: f32,
+}
+
+impl Sickness {
+    // Move the sickness forward by `seconds`: catch an ailment when the pet is neglected, let
+    // the current one wear the stats down, and tell when the pet is gone for good
+    pub fn progress(&mut self, stats: &mut PetStats, seconds: f32) -> Option<Fate> {
+        if self.ailment.is_none() {
+            if stats.hygiene < FLEAS_BELOW_HYGIENE {
+                self.ailment = Some(Ailment::Fleas);
+            } else if stats.health < FLU_BELOW_HEALTH {
+                self.ailment = Some(Ailment::Flu);
+            }
+        }
+        if let Some(ailment) = self.ailment {
+            ailment.wear(stats, seconds);
+        }
+        self.dying_for = if stats.health <= 0.0 {
+            self.dying_for + seconds
+        } else {
+            0.0
+        };
+        self.miserable_for = if stats.happiness <= 0.0 {
+            self.miserable_for + seconds
+        } else {
+            0.0
+        };
+        if self.dying_for >= DIES_AFTER {
+            Some(Fate::Died)
+        } else if self.miserable_for >= RUNS_AWAY_AFTER {
+            Some(Fate::RanAway)
+        } else {
+            None
+        }
+    }
+
+    // Heal the ailments `cures` is made for. Returns the ailment that was cured
+    pub fn treat(&mut self, cures: &[Ailment]) -> Option<Ailment> {
+        let ailment = self.ailment.filter(|ailment| cures.contains(ailment))?;
+        self.ailment = None;
+        Some(ailment)
+    }
+}
+
+// What is left of a pet that is gone
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Memorial {
+    pub name: String,
+    pub species: Species,
+    pub evolution: Option<String>,
+    // Age of the pet when it left, in seconds
+    pub age: f32,
+    pub fate: Fate,
+    // Unix timestamp (in seconds) of the day it left
+    pub at: u64,
+}
+
+impl Memorial {
+    pub fn new(profile: &PetProfile, age: &PetAge, growth: &Growth, fate: Fate) -> Self {
+        Memorial {
+            name: profile.name.clone(),
+            species: profile.species,
+            evolution: growth.evolution.clone(),
+            age: age.0,
+            fate,
+            at: now(),
+        }
+    }
+}
+
+// Every pet that died or ran away during this game, oldest first
+#[derive(Resource, Clone, Debug, Default, Deref, DerefMut)]
+pub struct Graveyard(pub Vec<Memorial>);
+
+// Present when a pet just left and its memorial should be shown
+#[derive(Resource)]
+pub struct MemorialPending;
+
+fn check_health(
+    mut commands: Commands,
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut graveyard: ResMut<Graveyard>,
+    mut feedback: EventWriter<PetFeedback>,
+    mut pets: Query<
+        (
+            Entity,
+            &PetProfile,
+            &PetAge,
+            &Growth,
+            &mut PetStats,
+            &mut Sickness,
+        ),
+        With<Pet>,
+    >,
+) {
+    for (entity, profile, age, growth, mut stats, mut sickness) in &mut pets {
+        let was_sick = sickness.ailment.is_some();
+        let fate = sickness.progress(&mut stats, time.delta_seconds());
+        if let (false, Some(ailment)) = (was_sick, sickness.ailment) {
+            feedback.send(PetFeedback(format!(
+                "{} caught {}!",
+                profile.name,
+                ailment.name()
+            )));
+        }
+        let Some(fate) = fate else {
+            continue;
+        };
+        graveyard.push(Memorial::new(profile, age, growth, fate));
+        commands.entity(entity).despawn_recursive();
+        commands.insert_resource(MemorialPending);
+        // On the menu screens, the memorial is opened from there
+        if state.0 == GameState::Game {
+            game_state.set(GameState::Menu);
+        }
+    }
+}
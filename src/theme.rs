@@ -0,0 +1,156 @@
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use serde::{Deserialize, Deserializer};
+
+use super::loading::{asset_changed, RonLoader};
+use super::settings::ThemePreset;
+
+// This plugin keeps `UiTheme` in line with the preset picked in the settings. The presets are
+// read from `assets/ui/<preset>.theme.ron` and picked up again when they change on disk
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ThemeFile>()
+            .add_asset_loader(RonLoader::<ThemeFile>::new(&["theme.ron"]))
+            .init_resource::<UiTheme>()
+            .add_startup_system(load_themes)
+            .add_system(apply_theme);
+    }
+}
+
+// Colours, font and text size of the menus. Every screen reads it when it is spawned, and is
+// spawned again when it changes
+#[derive(Resource, Deserialize, Clone, Debug, PartialEq)]
+pub struct UiTheme {
+    // Path of the font in the assets, loaded into `font`
+    pub font_path: String,
+    #[serde(skip)]
+    pub font: Handle<Font>,
+    // Every text size is multiplied by this
+    pub text_scale: f32,
+    #[serde(deserialize_with = "hex_color")]
+    pub text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub disabled_text: Color,
+    // Messages and warnings that should catch the eye
+    #[serde(deserialize_with = "hex_color")]
+    pub highlight: Color,
+    // Bad news, like a sick pet or a stat about to run out
+    #[serde(deserialize_with = "hex_color")]
+    pub danger: Color,
+    // A stat doing fine
+    #[serde(deserialize_with = "hex_color")]
+    pub good: Color,
+    // Background of the menu screens
+    #[serde(deserialize_with = "hex_color")]
+    pub panel: Color,
+    // Background of the menus shown over the game
+    #[serde(deserialize_with = "hex_color")]
+    pub bar: Color,
+    // Background of the memorial screen
+    #[serde(deserialize_with = "hex_color")]
+    pub memorial: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub hovered_button: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub pressed_button: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub hovered_pressed_button: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub disabled_button: Color,
+}
+
+// The dark preset, used until the theme file is loaded
+impl FromWorld for UiTheme {
+    fn from_world(world: &mut World) -> Self {
+        let font_path = "fonts/FiraSans-Bold.ttf".to_string();
+        UiTheme {
+            font: world.resource::<AssetServer>().load(font_path.as_str()),
+            font_path,
+            text_scale: 1.0,
+            text: Color::rgb(0.9, 0.9, 0.9),
+            disabled_text: Color::rgb(0.45, 0.45, 0.45),
+            highlight: Color::ORANGE,
+            danger: Color::RED,
+            good: Color::GREEN,
+            panel: Color::DARK_GREEN,
+            bar: Color::ORANGE,
+            memorial: Color::DARK_GRAY,
+            button: Color::rgb(0.15, 0.15, 0.15),
+            hovered_button: Color::rgb(0.25, 0.25, 0.25),
+            pressed_button: Color::rgb(0.35, 0.75, 0.35),
+            hovered_pressed_button: Color::rgb(0.25, 0.65, 0.25),
+            disabled_button: Color::rgb(0.1, 0.1, 0.1),
+        }
+    }
+}
+
+impl UiTheme {
+    // Style of a text of `size` (before scaling) in the normal text colour
+    pub fn text_style(&self, size: f32) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: size * self.text_scale,
+            color: self.text,
+        }
+    }
+}
+
+// Colours are written like "#262626" in the theme files
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    Color::hex(&hex).map_err(|err| serde::de::Error::custom(format!("{hex:?}: {err:?}")))
+}
+
+// A theme preset as read from its file
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "9a4c2e71-0b6d-4f3a-8e15-d7f2c09b6a38"]
+#[serde(transparent)]
+pub struct ThemeFile(pub UiTheme);
+
+// Files of every preset, all kept loaded so any of them can be switched to at once
+#[derive(Resource, Default)]
+struct ThemeFiles(HashMap<ThemePreset, Handle<ThemeFile>>);
+
+pub fn theme_path(preset: ThemePreset) -> String {
+    format!("ui/{}.theme.ron", preset.file_name())
+}
+
+fn load_themes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let files = ThemePreset::ALL
+        .into_iter()
+        .map(|preset| (preset, asset_server.load(theme_path(preset))))
+        .collect();
+    commands.insert_resource(ThemeFiles(files));
+}
+
+// Switch to the preset picked in the settings, or to its new version when its file changed
+fn apply_theme(
+    preset: Res<ThemePreset>,
+    files: Res<ThemeFiles>,
+    themes: Res<Assets<ThemeFile>>,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<AssetEvent<ThemeFile>>,
+    mut theme: ResMut<UiTheme>,
+) {
+    let Some(handle) = files.0.get(&*preset) else {
+        return;
+    };
+    let reloaded = asset_changed(&mut events, handle);
+    if !reloaded && !preset.is_changed() {
+        return;
+    }
+    let Some(ThemeFile(new_theme)) = themes.get(handle) else {
+        return;
+    };
+    let new_theme = UiTheme {
+        font: asset_server.load(new_theme.font_path.as_str()),
+        ..new_theme.clone()
+    };
+    // Changing the theme respawns the open screens, only do it when there is something new
+    if *theme != new_theme {
+        *theme = new_theme;
+    }
+}
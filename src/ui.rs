@@ -0,0 +1,297 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, reflect::TypeUuid};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::loading::RonLoader;
+use super::navigation::Focused;
+use super::theme::UiTheme;
+
+// This plugin holds what the screens of the game and the menu share: the system colouring
+// buttons as they are used, the helpers spawning the screens, panels and buttons, and
+// `spawn_menu` to build a menu from its description. Menus are described in
+// `assets/ui/*.menu.ron`
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<MenuFile>()
+            .add_asset_loader(RonLoader::<MenuFile>::new(&["menu.ron"]))
+            .add_system(button_system);
+    }
+}
+
+// Tag component used to mark which setting is currently selected
+#[derive(Component)]
+pub struct SelectedOption;
+
+// Tag component used to mark buttons that can't be used right now
+#[derive(Component)]
+pub struct DisabledButton;
+
+// This system handles changing all buttons color based on mouse interaction, the button
+// focused from the keyboard or a gamepad looks hovered
+fn button_system(
+    theme: Res<UiTheme>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            Option<&SelectedOption>,
+            Option<&Focused>,
+        ),
+        (Changed<Interaction>, With<Button>, Without<DisabledButton>),
+    >,
+) {
+    for (interaction, mut color, selected, focused) in &mut interaction_query {
+        let interaction = match (*interaction, focused) {
+            (Interaction::None, Some(_)) => Interaction::Hovered,
+            (interaction, _) => interaction,
+        };
+        *color = match (interaction, selected) {
+            (Interaction::Clicked, _) | (Interaction::None, Some(_)) => theme.pressed_button.into(),
+            (Interaction::Hovered, Some(_)) => theme.hovered_pressed_button.into(),
+            (Interaction::Hovered, None) => theme.hovered_button.into(),
+            (Interaction::None, None) => theme.button.into(),
+        }
+    }
+}
+
+// How the buttons of a menu are laid out
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MenuLayout {
+    // Big buttons on top of each other, for the menu screens
+    Column,
+    // Small buttons side by side, for the bars shown over the game
+    Bar,
+}
+
+impl MenuLayout {
+    // Size of a button, and the space around it horizontally and vertically
+    fn button_box(self) -> (Vec2, Vec2) {
+        match self {
+            MenuLayout::Column => (Vec2::new(250.0, 65.0), Vec2::new(10.0, 10.0)),
+            MenuLayout::Bar => (Vec2::new(86.0, 40.0), Vec2::new(7.0, 8.0)),
+        }
+    }
+}
+
+// A button of a menu: its text, an icon shown on its left and what clicking it does
+#[derive(Deserialize, Clone, Debug)]
+pub struct MenuItem<A> {
+    pub label: String,
+    // Path of the icon in the assets
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub action: A,
+}
+
+// Everything needed to spawn a menu, `A` is the component telling what a button does
+#[derive(Deserialize, Clone, Debug)]
+pub struct MenuDef<A> {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub items: Vec<MenuItem<A>>,
+    pub layout: MenuLayout,
+}
+
+impl<A> MenuDef<A> {
+    // Size of the spawned panel, leaving the title out
+    pub fn size(&self) -> Vec2 {
+        let (button, margin) = self.layout.button_box();
+        let slot = button + 2.0 * margin;
+        let count = self.items.len() as f32;
+        match self.layout {
+            MenuLayout::Column => Vec2::new(slot.x, slot.y * count),
+            MenuLayout::Bar => Vec2::new(slot.x * count, slot.y),
+        }
+    }
+}
+
+// A menu as written in `assets/ui/*.menu.ron`. Actions are the names of the variants of the
+// button component of the screen, like "NewGame" for `MenuButtonAction::NewGame`
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "3d9e6b27-5a1c-4f08-b7e2-c46a18f0d953"]
+#[serde(transparent)]
+pub struct MenuFile(pub MenuDef<String>);
+
+impl MenuFile {
+    // The menu with its actions read as `A`. Items with an action `A` doesn't know are left out
+    pub fn menu<A: DeserializeOwned>(&self) -> MenuDef<A> {
+        let items = self
+            .0
+            .items
+            .iter()
+            .filter_map(|item| match ron::from_str(&item.action) {
+                Ok(action) => Some(MenuItem {
+                    label: item.label.clone(),
+                    icon: item.icon.clone(),
+                    action,
+                }),
+                Err(err) => {
+                    warn!("Unknown menu action {:?}: {err}", item.action);
+                    None
+                }
+            })
+            .collect();
+        MenuDef {
+            title: self.0.title.clone(),
+            items,
+            layout: self.0.layout,
+        }
+    }
+}
+
+// Node covering the whole window with its content centred in a column. It is tagged with the
+// component of the screen, so it is despawned along with everything on it
+pub fn spawn_screen<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    screen: impl Component,
+) -> EntityCommands<'w, 's, 'a> {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        screen,
+    ))
+}
+
+// Column of text and buttons on a `background`, the box most screens show their content in
+pub fn spawn_panel<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    background: Color,
+    padding: f32,
+) -> EntityCommands<'w, 's, 'a> {
+    parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(padding)),
+            ..default()
+        },
+        background_color: background.into(),
+        ..default()
+    })
+}
+
+// Style of a button of the given size, with what is on it centred
+pub fn button_style(width: f32, height: f32, margin: f32) -> Style {
+    Style {
+        size: Size::new(Val::Px(width), Val::Px(height)),
+        margin: UiRect::all(Val::Px(margin)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+// A button in the colours of the theme, with nothing on it yet
+pub fn button_bundle(theme: &UiTheme, style: Style) -> ButtonBundle {
+    ButtonBundle {
+        style,
+        background_color: theme.button.into(),
+        ..default()
+    }
+}
+
+// Spawn a button with `label` written on it, `action` tells what clicking it does
+pub fn spawn_button<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    theme: &UiTheme,
+    style: Style,
+    label: impl Into<String>,
+    text_style: TextStyle,
+    action: impl Bundle,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut button = parent.spawn((button_bundle(theme, style), action));
+    button.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, text_style));
+    });
+    button
+}
+
+// Spawn the panel of `menu` under `parent`, with a button per item in order. The buttons of
+// actions that aren't `enabled` are greyed out and do nothing
+pub fn spawn_menu<'w, 's, 'a, A: Component + Clone>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    asset_server: &AssetServer,
+    theme: &UiTheme,
+    menu: &MenuDef<A>,
+    enabled: impl Fn(&A) -> bool,
+) -> EntityCommands<'w, 's, 'a> {
+    let (direction, background) = match menu.layout {
+        MenuLayout::Column => (FlexDirection::Column, theme.panel),
+        MenuLayout::Bar => (FlexDirection::Row, theme.bar),
+    };
+    let (button_size, button_margin) = menu.layout.button_box();
+    // Common style for all buttons of the menu
+    let button_style = Style {
+        margin: UiRect::new(
+            Val::Px(button_margin.x),
+            Val::Px(button_margin.x),
+            Val::Px(button_margin.y),
+            Val::Px(button_margin.y),
+        ),
+        ..button_style(button_size.x, button_size.y, 0.0)
+    };
+    let button_icon_style = Style {
+        size: Size::new(Val::Px(30.0), Val::Auto),
+        // This takes the icons out of the flexbox flow, to be positioned exactly
+        position_type: PositionType::Absolute,
+        // The icon will be close to the left border of the button
+        position: UiRect {
+            left: Val::Px(10.0),
+            right: Val::Auto,
+            top: Val::Auto,
+            bottom: Val::Auto,
+        },
+        ..default()
+    };
+    let button_text_style = theme.text_style(25.0);
+
+    let mut panel = parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: direction,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: background.into(),
+        ..default()
+    });
+    panel.with_children(|parent| {
+        if let Some(title) = &menu.title {
+            parent.spawn(
+                TextBundle::from_section(title.clone(), theme.text_style(30.0)).with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+        }
+        for item in &menu.items {
+            let mut button = parent.spawn(button_bundle(theme, button_style.clone()));
+            let mut text_style = button_text_style.clone();
+            if enabled(&item.action) {
+                button.insert(item.action.clone());
+            } else {
+                button.insert((DisabledButton, BackgroundColor(theme.disabled_button)));
+                text_style.color = theme.disabled_text;
+            }
+            button.with_children(|parent| {
+                if let Some(icon) = &item.icon {
+                    parent.spawn(ImageBundle {
+                        style: button_icon_style.clone(),
+                        image: UiImage::new(asset_server.load(icon.as_str())),
+                        ..default()
+                    });
+                }
+                parent.spawn(TextBundle::from_section(item.label.clone(), text_style));
+            });
+        }
+    });
+    panel
+}
@@ -0,0 +1,247 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use super::anim::SpriteAnimation;
+use super::game::PlayMenuState;
+use super::growth::{Growth, GrowthStage};
+use super::pet::{Pet, PetActivity};
+use super::settings::WidgetMode;
+use super::GameState;
+
+// This plugin makes an idle pet stroll around the window: it walks to a random spot, rests for
+// a while and sets off again
+pub struct WanderPlugin;
+
+impl Plugin for WanderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WanderRng>()
+            .add_systems((attach_wander, wander).chain());
+    }
+}
+
+// Walking speed of the pet, in pixels per second
+const WALK_SPEED: f32 = 60.0;
+// Shortest and longest rest between two walks, in seconds
+const REST_MIN: f32 = 1.0;
+const REST_MAX: f32 = 5.0;
+
+// Random numbers of the wandering. Set the `PET_FUN_SEED` environment variable to a number to
+// get the same walks on every run
+#[derive(Resource)]
+pub struct WanderRng(pub fastrand::Rng);
+
+impl Default for WanderRng {
+    fn default() -> Self {
+        let seed = std::env::var("PET_FUN_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok());
+        WanderRng(match seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        })
+    }
+}
+
+// What the pet is doing on its stroll
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum Wander {
+    // Seconds left before walking again
+    Resting(f32),
+    // Walking to this point, in world coordinates
+    Walking(Vec2),
+}
+
+impl Default for Wander {
+    fn default() -> Self {
+        Wander::Resting(REST_MIN)
+    }
+}
+
+impl Wander {
+    // Move the pet at `position` for `seconds`, never leaving `bounds`. Returns its new position
+    pub fn step(
+        &mut self,
+        position: Vec2,
+        bounds: Rect,
+        seconds: f32,
+        rng: &mut fastrand::Rng,
+    ) -> Vec2 {
+        let position = position.clamp(bounds.min, bounds.max);
+        match *self {
+            Wander::Resting(left) if left > seconds => {
+                *self = Wander::Resting(left - seconds);
+                position
+            }
+            Wander::Resting(_) => {
+                *self =
+                    Wander::Walking(bounds.min + Vec2::new(rng.f32(), rng.f32()) * bounds.size());
+                position
+            }
+            Wander::Walking(target) => {
+                // The window may have shrunk since the target was picked
+                let target = target.clamp(bounds.min, bounds.max);
+                let distance = position.distance(target);
+                let walked = WALK_SPEED * seconds;
+                if distance <= walked {
+                    *self = Wander::Resting(REST_MIN + rng.f32() * (REST_MAX - REST_MIN));
+                    target
+                } else {
+                    position + (target - position) / distance * walked
+                }
+            }
+        }
+    }
+
+    pub fn is_walking(&self) -> bool {
+        matches!(self, Wander::Walking(_))
+    }
+}
+
+fn attach_wander(mut commands: Commands, pets: Query<Entity, (With<Pet>, Without<Wander>)>) {
+    for pet in &pets {
+        commands.entity(pet).insert(Wander::default());
+    }
+}
+
+// Walk the pet around while it is idle and nothing else is going on. It stands still while
+// doing something else, while a menu is open and in widget mode, where the window follows the
+// pet instead
+#[allow(clippy::too_many_arguments)]
+fn wander(
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    play_menu_state: Res<State<PlayMenuState>>,
+    widget_mode: Res<WidgetMode>,
+    mut rng: ResMut<WanderRng>,
+    atlases: Res<Assets<TextureAtlas>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut pets: Query<
+        (
+            &PetActivity,
+            &Growth,
+            &Handle<TextureAtlas>,
+            &mut Wander,
+            &mut Transform,
+            &mut TextureAtlasSprite,
+            &mut SpriteAnimation,
+        ),
+        With<Pet>,
+    >,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.resolution.width(), window.resolution.height());
+    for (activity, growth, atlas, mut wander, mut transform, mut sprite, mut animation) in &mut pets
+    {
+        let free = game_state.0 == GameState::Game
+            && play_menu_state.0 == PlayMenuState::Disable
+            && !widget_mode.0
+            && growth.stage != GrowthStage::Egg
+            && matches!(activity, PetActivity::Idle | PetActivity::Awake);
+        if !free {
+            if widget_mode.0 {
+                // The widget window is centred on the pet
+                transform.translation.x = 0.0;
+                transform.translation.y = 0.0;
+            }
+            if wander.is_walking() {
+                *wander = Wander::default();
+                animation.play(activity.clip());
+            }
+            continue;
+        }
+        // Keep the whole sprite in the window
+        let pet_size = atlases
+            .get(atlas)
+            .and_then(|atlas| atlas.textures.get(sprite.index))
+            .map_or(Vec2::ZERO, |frame| {
+                frame.size() * transform.scale.truncate()
+            });
+        let bounds = Rect::from_center_size(Vec2::ZERO, (window_size - pet_size).max(Vec2::ZERO));
+        let position = transform.translation.truncate();
+        let next = wander.step(position, bounds, time.delta_seconds(), &mut rng.0);
+        // Face where the pet is going, the sheets are drawn looking right
+        if next.x != position.x {
+            sprite.flip_x = next.x < position.x;
+        }
+        transform.translation.x = next.x;
+        transform.translation.y = next.y;
+        animation.play(if wander.is_walking() {
+            "run"
+        } else {
+            activity.clip()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds() -> Rect {
+        Rect::from_center_size(Vec2::ZERO, Vec2::new(400.0, 300.0))
+    }
+
+    // Positions of a pet wandering for `steps` frames of 1/30 s
+    fn walk(seed: u64, steps: usize) -> Vec<Vec2> {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut wander = Wander::default();
+        let mut position = Vec2::ZERO;
+        (0..steps)
+            .map(|_| {
+                position = wander.step(position, bounds(), 1.0 / 30.0, &mut rng);
+                position
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_walk() {
+        assert_eq!(walk(42, 600), walk(42, 600));
+        assert_ne!(walk(42, 600), walk(7, 600));
+    }
+
+    #[test]
+    fn stays_within_bounds() {
+        for position in walk(3, 3000) {
+            assert!(bounds().contains(position), "{position} is out of bounds");
+        }
+    }
+
+    #[test]
+    fn rests_before_picking_a_target() {
+        let mut rng = fastrand::Rng::with_seed(1);
+        let mut wander = Wander::Resting(0.5);
+        let position = wander.step(Vec2::ZERO, bounds(), 0.25, &mut rng);
+        assert_eq!(position, Vec2::ZERO);
+        assert_eq!(wander, Wander::Resting(0.25));
+        wander.step(position, bounds(), 0.25, &mut rng);
+        let Wander::Walking(target) = wander else {
+            panic!("expected to walk, got {wander:?}");
+        };
+        assert!(bounds().contains(target));
+    }
+
+    #[test]
+    fn walks_at_walk_speed_then_rests_on_target() {
+        let mut rng = fastrand::Rng::with_seed(1);
+        let target = Vec2::new(150.0, 0.0);
+        let mut wander = Wander::Walking(target);
+        let position = wander.step(Vec2::ZERO, bounds(), 1.0, &mut rng);
+        assert_eq!(position, Vec2::new(WALK_SPEED, 0.0));
+        assert_eq!(wander, Wander::Walking(target));
+
+        let position = wander.step(position, bounds(), 100.0, &mut rng);
+        assert_eq!(position, target);
+        assert!(matches!(wander, Wander::Resting(rest) if (REST_MIN..=REST_MAX).contains(&rest)));
+    }
+
+    #[test]
+    fn walks_back_into_a_shrunk_window() {
+        let mut rng = fastrand::Rng::with_seed(5);
+        let mut wander = Wander::Walking(Vec2::new(150.0, 0.0));
+        let small = Rect::from_center_size(Vec2::ZERO, Vec2::new(100.0, 100.0));
+        let position = wander.step(Vec2::new(150.0, 100.0), small, 0.0, &mut rng);
+        assert!(small.contains(position));
+    }
+}
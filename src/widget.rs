@@ -0,0 +1,181 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowMoved},
+    winit::WinitWindows,
+};
+
+use super::actions::{Action, Actions};
+use super::game::PlayMenuState;
+use super::pet::PetPicker;
+use super::settings::{WidgetMode, WindowPlacement};
+use super::GameState;
+
+// This plugin turns the window into a desktop widget when `WidgetMode` is on: the decorations
+// are removed, the window shrinks around the pet while playing and the pet can be dragged
+// around the screen
+pub struct WidgetPlugin;
+
+impl Plugin for WidgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(restore_position).add_systems((
+            toggle_widget_mode,
+            seed_position,
+            fit_window,
+            drag_window,
+            track_position,
+        ));
+    }
+}
+
+// Size of the window outside of widget mode, and while a menu is open in widget mode
+pub const WINDOW_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+
+// Space left around the pet in widget mode, in logical pixels
+const WIDGET_MARGIN: f32 = 16.0;
+
+// Put the window back where it was when the game was closed
+fn restore_position(
+    placement: Res<WindowPlacement>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Some(position) = placement.0 else {
+        return;
+    };
+    for mut window in &mut windows {
+        window.position = WindowPosition::At(position);
+    }
+}
+
+// Ask the OS where the window is until it is known. With no saved placement it is left to the
+// OS at startup, and the window can't be dragged or kept in place when resized without it
+fn seed_position(
+    winit_windows: NonSend<WinitWindows>,
+    mut windows: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+) {
+    for (entity, mut window) in &mut windows {
+        if matches!(window.position, WindowPosition::At(_)) {
+            continue;
+        }
+        let Some(position) = winit_windows
+            .get_window(entity)
+            .and_then(|winit_window| winit_window.outer_position().ok())
+        else {
+            continue;
+        };
+        window.position = WindowPosition::At(IVec2::new(position.x, position.y));
+    }
+}
+
+// Switch widget mode on and off without going through the settings
+fn toggle_widget_mode(actions: Actions, mut widget_mode: ResMut<WidgetMode>) {
+    if actions.just_pressed(Action::ToggleWidget) {
+        widget_mode.0 = !widget_mode.0;
+    }
+}
+
+// Size the window to the pet while playing in widget mode, and back to normal otherwise. The
+// window grows and shrinks around its centre, where the pet stands in widget mode, so the pet
+// doesn't jump across the desktop
+pub fn fit_window(
+    widget_mode: Res<WidgetMode>,
+    game_state: Res<State<GameState>>,
+    play_menu_state: Res<State<PlayMenuState>>,
+    // The picker reads the window that is resized here
+    mut window_access: ParamSet<(PetPicker, Query<&mut Window, With<PrimaryWindow>>)>,
+) {
+    let compact = widget_mode.0
+        && game_state.0 == GameState::Game
+        && play_menu_state.0 == PlayMenuState::Disable;
+    let size = if compact {
+        // Keep the current size until the pet is shown
+        match window_access.p0().pet_rect() {
+            Some(rect) => rect.size() + 2.0 * WIDGET_MARGIN,
+            None => return,
+        }
+    } else {
+        WINDOW_SIZE
+    };
+    for mut window in &mut window_access.p1() {
+        // Only touch the window when something changes, setting it marks it as changed
+        if window.decorations == widget_mode.0 {
+            window.decorations = !widget_mode.0;
+        }
+        let current = Vec2::new(window.resolution.width(), window.resolution.height());
+        if (current - size).abs().max_element() > 0.5 {
+            window.resolution.set(size.x, size.y);
+            // The position is the top left corner in physical pixels
+            if let WindowPosition::At(position) = window.position {
+                let shift = (current - size) / 2.0 * window.scale_factor() as f32;
+                window.position = WindowPosition::At(position + shift.round().as_ivec2());
+            }
+        }
+    }
+}
+
+// Move the window along with the cursor while the pet is grabbed.
+// `grab` is where the cursor was in the window when the pet was picked up
+fn drag_window(
+    actions: Actions,
+    widget_mode: Res<WidgetMode>,
+    mut grab: Local<Option<Vec2>>,
+    // The picker reads the window that is moved here
+    mut window_access: ParamSet<(PetPicker, Query<&mut Window, With<PrimaryWindow>>)>,
+) {
+    if !widget_mode.0 || actions.just_released(Action::GrabPet) {
+        *grab = None;
+        return;
+    }
+    if actions.just_pressed(Action::GrabPet) && window_access.p0().cursor_on_pet() {
+        *grab = window_access
+            .p1()
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position());
+    }
+    let mut windows = window_access.p1();
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    // The release may have been missed while the cursor was out of the window
+    if !actions.held(Action::GrabPet) {
+        *grab = None;
+        return;
+    }
+    let (Some(grabbed_at), Some(cursor)) = (*grab, window.cursor_position()) else {
+        return;
+    };
+    // The window position is in physical pixels with y going down, the cursor is in logical
+    // pixels with y going up
+    let WindowPosition::At(position) = window.position else {
+        return;
+    };
+    let delta = (cursor - grabbed_at) * window.scale_factor() as f32;
+    if delta.length_squared() >= 1.0 {
+        window.position =
+            WindowPosition::At(position + IVec2::new(delta.x as i32, -delta.y as i32));
+    }
+}
+
+// Remember where the window is, once it stops moving so the settings aren't written to disk
+// on every step of a drag
+fn track_position(
+    mut moves: EventReader<WindowMoved>,
+    primary: Query<Entity, With<PrimaryWindow>>,
+    mut placement: ResMut<WindowPlacement>,
+    mut moving: Local<bool>,
+) {
+    let Ok(primary) = primary.get_single() else {
+        return;
+    };
+    match moves.iter().filter(|moved| moved.entity == primary).last() {
+        Some(moved) => {
+            placement.bypass_change_detection().0 = Some(moved.position);
+            *moving = true;
+        }
+        None if *moving => {
+            placement.set_changed();
+            *moving = false;
+        }
+        None => {}
+    }
+}